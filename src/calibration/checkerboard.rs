@@ -0,0 +1,367 @@
+use std::collections::{HashMap, VecDeque};
+
+use glam::Vec2;
+
+use crate::imgproc::GrayImage;
+
+const BLUR_SIGMA: f32 = 1.5;
+const NMS_RADIUS: usize = 4;
+const RELATIVE_THRESHOLD: f32 = 0.1;
+const RING_RADIUS: f32 = 5.0;
+const RING_SAMPLES: usize = 32;
+const MIN_RING_CONTRAST: f32 = 0.15;
+const SUBPIXEL_ITERATIONS: usize = 4;
+const GRID_TOLERANCE: f32 = 0.35;
+const SEED_ATTEMPTS: usize = 8;
+
+/// Inner corner layout of a checkerboard.
+#[derive(Debug, Clone, Copy)]
+pub struct Board {
+    /// Inner corners along a row.
+    pub cols: usize,
+    /// Inner corners along a column.
+    pub rows: usize,
+    /// Edge length of one square, in world units.
+    pub square: f32,
+}
+
+impl Board {
+    pub fn len(&self) -> usize {
+        self.cols * self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Corner positions on the board plane, in the same order as
+    /// [`find_corners`] returns them.
+    pub fn object_points(&self) -> Vec<Vec2> {
+        (0..self.rows)
+            .flat_map(|j| (0..self.cols).map(move |i| Vec2::new(i as f32, j as f32)))
+            .map(|p| p * self.square)
+            .collect()
+    }
+}
+
+/// Finds the inner corners of `board` in `img`, row by row. Returns `None` when
+/// the full board couldn't be found.
+pub fn find_corners(img: &GrayImage, board: &Board) -> Option<Vec<Vec2>> {
+    let blurred = img.blur(BLUR_SIGMA);
+    let candidates = saddle_points(&blurred);
+
+    if candidates.len() < board.len() {
+        return None;
+    }
+
+    let mut seeds: Vec<usize> = (0..candidates.len()).collect();
+    let centroid = candidates.iter().copied().sum::<Vec2>() / candidates.len() as f32;
+    seeds.sort_by(|&a, &b| {
+        candidates[a]
+            .distance_squared(centroid)
+            .total_cmp(&candidates[b].distance_squared(centroid))
+    });
+
+    seeds
+        .into_iter()
+        .take(SEED_ATTEMPTS)
+        .find_map(|seed| grow_grid(&candidates, seed, board))
+}
+
+/// Saddle point candidates with sub-pixel positions.
+fn saddle_points(img: &GrayImage) -> Vec<Vec2> {
+    let (w, h) = (img.width(), img.height());
+    let margin = NMS_RADIUS.max(RING_RADIUS.ceil() as usize + 1);
+    if w <= 2 * margin || h <= 2 * margin {
+        return vec![];
+    }
+
+    let mut response = vec![0.0f32; w * h];
+    let mut max_response = 0.0f32;
+
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let [xx, xy, yy] = hessian(img, x, y);
+            // negative hessian determinant is positive on saddles
+            let r = xy * xy - xx * yy;
+            response[y * w + x] = r;
+            max_response = max_response.max(r);
+        }
+    }
+
+    if max_response <= 0.0 {
+        return vec![];
+    }
+    let threshold = max_response * RELATIVE_THRESHOLD;
+
+    let mut points = vec![];
+    for y in margin..h - margin {
+        for x in margin..w - margin {
+            let r = response[y * w + x];
+            if r < threshold {
+                continue;
+            }
+
+            let is_max = (y - NMS_RADIUS..=y + NMS_RADIUS).all(|ny| {
+                (x - NMS_RADIUS..=x + NMS_RADIUS).all(|nx| {
+                    let other = response[ny * w + nx];
+                    other < r || (other == r && (ny, nx) >= (y, x))
+                })
+            });
+
+            if is_max && is_checker_corner(img, Vec2::new(x as f32, y as f32)) {
+                if let Some(p) = refine(img, Vec2::new(x as f32, y as f32)) {
+                    points.push(p);
+                }
+            }
+        }
+    }
+
+    points
+}
+
+/// Hessian `[xx, xy, yy]` by central differences.
+fn hessian(img: &GrayImage, x: usize, y: usize) -> [f32; 3] {
+    let c = img.get(x, y);
+    let xx = img.get(x + 1, y) - 2.0 * c + img.get(x - 1, y);
+    let yy = img.get(x, y + 1) - 2.0 * c + img.get(x, y - 1);
+    let xy = (img.get(x + 1, y + 1) - img.get(x + 1, y - 1) - img.get(x - 1, y + 1)
+        + img.get(x - 1, y - 1))
+        / 4.0;
+
+    [xx, xy, yy]
+}
+
+/// A checkerboard corner alternates between dark and light exactly four times
+/// on a ring around it.
+fn is_checker_corner(img: &GrayImage, p: Vec2) -> bool {
+    let ring: Vec<f32> = (0..RING_SAMPLES)
+        .map(|i| {
+            let a = i as f32 / RING_SAMPLES as f32 * std::f32::consts::TAU;
+            img.sample(p + RING_RADIUS * Vec2::from_angle(a))
+        })
+        .collect();
+
+    let (min, max) = ring
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    if max - min < MIN_RING_CONTRAST {
+        return false;
+    }
+
+    let mid = (min + max) / 2.0;
+    let changes = (0..RING_SAMPLES)
+        .filter(|&i| (ring[i] > mid) != (ring[(i + 1) % RING_SAMPLES] > mid))
+        .count();
+
+    changes == 4
+}
+
+/// Moves `p` to the stationary point of the local quadratic intensity model.
+fn refine(img: &GrayImage, mut p: Vec2) -> Option<Vec2> {
+    let start = p;
+
+    for _ in 0..SUBPIXEL_ITERATIONS {
+        let s = |dx: f32, dy: f32| img.sample(p + Vec2::new(dx, dy));
+        let gx = (s(1.0, 0.0) - s(-1.0, 0.0)) / 2.0;
+        let gy = (s(0.0, 1.0) - s(0.0, -1.0)) / 2.0;
+        let xx = s(1.0, 0.0) - 2.0 * s(0.0, 0.0) + s(-1.0, 0.0);
+        let yy = s(0.0, 1.0) - 2.0 * s(0.0, 0.0) + s(0.0, -1.0);
+        let xy = (s(1.0, 1.0) - s(1.0, -1.0) - s(-1.0, 1.0) + s(-1.0, -1.0)) / 4.0;
+
+        let det = xx * yy - xy * xy;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let step = Vec2::new(-(yy * gx - xy * gy) / det, -(xx * gy - xy * gx) / det);
+        p += step.clamp_length_max(1.0);
+
+        if step.length_squared() < 1e-6 {
+            break;
+        }
+    }
+
+    (p.distance(start) < 2.0).then_some(p)
+}
+
+/// Grows a lattice of corners outwards from `seed` and checks it matches `board`.
+fn grow_grid(points: &[Vec2], seed: usize, board: &Board) -> Option<Vec<Vec2>> {
+    let mut by_distance: Vec<usize> = (0..points.len()).filter(|&i| i != seed).collect();
+    by_distance.sort_by(|&a, &b| {
+        points[a]
+            .distance_squared(points[seed])
+            .total_cmp(&points[b].distance_squared(points[seed]))
+    });
+
+    let first = *by_distance.first()?;
+    let u = points[first] - points[seed];
+    let second = by_distance.iter().skip(1).take(6).copied().find(|&i| {
+        let v = points[i] - points[seed];
+        let ratio = v.length() / u.length();
+        u.normalize().dot(v.normalize()).abs() < 0.5 && (0.5..2.0).contains(&ratio)
+    })?;
+    let v = points[second] - points[seed];
+
+    let mut grid: HashMap<(i32, i32), usize> = HashMap::new();
+    let mut used = vec![false; points.len()];
+    let mut queue = VecDeque::new();
+
+    for (idx, key) in [(seed, (0, 0)), (first, (1, 0)), (second, (0, 1))] {
+        grid.insert(key, idx);
+        used[idx] = true;
+        queue.push_back(key);
+    }
+
+    while let Some((i, j)) = queue.pop_front() {
+        let here = points[grid[&(i, j)]];
+
+        for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let target = (i + di, j + dj);
+            if grid.contains_key(&target) {
+                continue;
+            }
+
+            // continue the lattice from the opposite neighbour if there is one
+            let step = match grid.get(&(i - di, j - dj)) {
+                Some(&back) => here - points[back],
+                None if di != 0 => u * di as f32,
+                None => v * dj as f32,
+            };
+            let predicted = here + step;
+            let tolerance = GRID_TOLERANCE * step.length();
+
+            let found = (0..points.len())
+                .filter(|&k| !used[k])
+                .map(|k| (k, points[k].distance(predicted)))
+                .filter(|&(_, d)| d < tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((k, _)) = found {
+                grid.insert(target, k);
+                used[k] = true;
+                queue.push_back(target);
+            }
+        }
+    }
+
+    order_grid(points, &grid, board)
+}
+
+/// Lays out a grown lattice row by row with image-right/image-down handedness.
+fn order_grid(
+    points: &[Vec2],
+    grid: &HashMap<(i32, i32), usize>,
+    board: &Board,
+) -> Option<Vec<Vec2>> {
+    let min_i = grid.keys().map(|k| k.0).min()?;
+    let min_j = grid.keys().map(|k| k.1).min()?;
+    let w = (grid.keys().map(|k| k.0).max()? - min_i + 1) as usize;
+    let h = (grid.keys().map(|k| k.1).max()? - min_j + 1) as usize;
+
+    // the handedness check below needs a step along each axis
+    if grid.len() != w * h || w < 2 || h < 2 {
+        return None;
+    }
+
+    let mut cells: Vec<Vec<Vec2>> = vec![vec![Vec2::ZERO; w]; h];
+    for (&(i, j), &idx) in grid {
+        cells[(j - min_j) as usize][(i - min_i) as usize] = points[idx];
+    }
+
+    if (w, h) == (board.rows, board.cols) && w != h {
+        cells = (0..w)
+            .map(|i| (0..h).map(|j| cells[j][i]).collect())
+            .collect();
+    } else if (w, h) != (board.cols, board.rows) {
+        return None;
+    }
+
+    let du = cells[0][1] - cells[0][0];
+    let dv = cells[1][0] - cells[0][0];
+    if du.perp_dot(dv) < 0.0 {
+        cells.iter_mut().for_each(|row| row.reverse());
+    }
+
+    let corners: Vec<Vec2> = cells.into_iter().flatten().collect();
+    let (first, last) = (corners[0], corners[corners.len() - 1]);
+    if first.x + first.y > last.x + last.y {
+        return Some(corners.into_iter().rev().collect());
+    }
+
+    Some(corners)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use glam::{DMat3, DVec3};
+
+    /// Renders `board` on a white background through the homography `h` from
+    /// board plane to pixels.
+    pub(crate) fn render_board(w: usize, hgt: usize, board: &Board, h: DMat3) -> GrayImage {
+        let inv = h.inverse();
+        let s = board.square as f64;
+        let samples = 3;
+
+        GrayImage::from_fn(w, hgt, |x, y| {
+            let mut acc = 0.0;
+            for sy in 0..samples {
+                for sx in 0..samples {
+                    let px = x as f64 + (sx as f64 + 0.5) / samples as f64 - 0.5;
+                    let py = y as f64 + (sy as f64 + 0.5) / samples as f64 - 0.5;
+                    let q = inv * DVec3::new(px, py, 1.0);
+                    let (bx, by) = (q.x / q.z / s + 1.0, q.y / q.z / s + 1.0);
+
+                    // squares cover [0, cols + 1) x [0, rows + 1), white outside
+                    let dark = bx >= 0.0
+                        && by >= 0.0
+                        && bx < board.cols as f64 + 1.0
+                        && by < board.rows as f64 + 1.0
+                        && (bx.floor() as i64 + by.floor() as i64) % 2 == 0;
+
+                    acc += if dark { 0.1 } else { 0.9 };
+                }
+            }
+            acc / (samples * samples) as f32
+        })
+    }
+
+    #[test]
+    fn finds_rendered_corners() {
+        let board = Board {
+            cols: 7,
+            rows: 5,
+            square: 1.0,
+        };
+        // slight perspective so the lattice isn't perfectly regular
+        let h = DMat3::from_cols(
+            DVec3::new(38.0, 4.0, 0.0004),
+            DVec3::new(-3.0, 36.0, 0.0006),
+            DVec3::new(120.0, 90.0, 1.0),
+        );
+        let img = render_board(480, 360, &board, h);
+
+        let corners = find_corners(&img, &board).expect("board not found");
+        assert_eq!(corners.len(), board.len());
+
+        for (found, obj) in corners.iter().zip(board.object_points()) {
+            let q = h * DVec3::new(obj.x as f64, obj.y as f64, 1.0);
+            let expected = Vec2::new((q.x / q.z) as f32, (q.y / q.z) as f32);
+            assert!(found.distance(expected) < 0.3, "{found} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn single_row_board_is_not_found() {
+        let board = Board {
+            cols: 3,
+            rows: 1,
+            square: 1.0,
+        };
+        let points = [0.0, 1.0, 2.0].map(|x| Vec2::new(x, 0.0));
+        let grid = HashMap::from([((0, 0), 0), ((1, 0), 1), ((2, 0), 2)]);
+
+        assert!(order_grid(&points, &grid, &board).is_none());
+    }
+}
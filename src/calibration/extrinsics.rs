@@ -0,0 +1,485 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use error_stack::{Result, ResultExt};
+use glam::{EulerRot, Quat, Vec2, Vec3A};
+use nalgebra::{DMatrix, DVector, Matrix3, Matrix3x4, Rotation3, UnitQuaternion, Vector3};
+use serde::Deserialize;
+
+use super::lm::{levenberg_marquardt, null_vector};
+use crate::{
+    config::CameraProperties,
+    math::{calc_local_dir_vec, project_local_dir},
+    GError, HasGlamPosition, HasGlamQuat, ImageCoords,
+};
+
+const MIN_POINTS: usize = 4;
+const DLT_MIN_POINTS: usize = 6;
+/// Largest RMS angle between the observed rays and the rays to the points, in
+/// radians, for a solved pose to count as converged. About 3 degrees.
+const MAX_RMS_ANGLE: f64 = 0.05;
+
+/// Surveyed world points and where they appear in each camera.
+///
+/// ```toml
+/// [[points]]
+/// name = "door frame"
+/// x = 1.2
+/// y = 0.0
+/// z = 2.0
+/// camera1 = [612.0, 140.5]
+/// camera2 = [80.0, 301.0]
+/// ```
+#[derive(Deserialize, Debug)]
+pub struct Survey {
+    pub points: Vec<SurveyPoint>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SurveyPoint {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// Pixel position keyed by camera table name.
+    #[serde(flatten)]
+    pub pixels: BTreeMap<String, [f32; 2]>,
+}
+
+/// A world point and the pixel it was observed at.
+#[derive(Debug, Clone)]
+pub struct Correspondence {
+    pub name: String,
+    pub world: Vec3A,
+    pub pixel: Vec2,
+}
+
+impl Survey {
+    pub fn open(path: &Path) -> Result<Self, GError> {
+        toml::from_str(
+            &fs::read_to_string(path)
+                .change_context(GError::CalibrationError)
+                .attach_printable("Couldn't read the survey file")?,
+        )
+        .change_context(GError::CalibrationError)
+    }
+
+    /// Points observed by `camera`.
+    pub fn correspondences(&self, camera: &str) -> Vec<Correspondence> {
+        self.points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| {
+                p.pixels.get(camera).map(|px| Correspondence {
+                    name: p.name.clone().unwrap_or_else(|| format!("#{i}")),
+                    world: Vec3A::new(p.x, p.y, p.z),
+                    pixel: Vec2::from(*px),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Fit quality of one correspondence.
+#[derive(Debug, Clone)]
+pub struct PointResidual {
+    pub name: String,
+    /// Distance between the observed and reprojected pixel. `None` if the
+    /// point ended up behind the camera.
+    pub pixels: Option<f32>,
+    /// Angle between the observed ray and the ray to the point, in radians.
+    pub angle: f32,
+}
+
+/// Solved camera pose, in the conventions of [`CameraProperties`].
+#[derive(Debug, Clone)]
+pub struct ExtrinsicCalibration {
+    pub pos: Vec3A,
+    pub quat: Quat,
+    pub residuals: Vec<PointResidual>,
+}
+
+impl ExtrinsicCalibration {
+    /// `(yaw, pitch, roll)` as stored in the config.
+    pub fn euler(&self) -> (f32, f32, f32) {
+        self.quat.to_euler(EulerRot::ZYX)
+    }
+
+    /// RMS of the pixel residuals of the points in front of the camera.
+    pub fn rms(&self) -> f32 {
+        let px: Vec<f32> = self.residuals.iter().filter_map(|r| r.pixels).collect();
+        (px.iter().map(|p| p * p).sum::<f32>() / px.len().max(1) as f32).sqrt()
+    }
+}
+
+/// Solves the pose of `camera` from world-pixel correspondences. The configured
+/// pose, a linear pose from the points and a pose from their best fitting
+/// plane are the starting points, the intrinsics are kept fixed. Fails when
+/// none of them converges.
+pub fn calibrate_extrinsics(
+    camera: &CameraProperties,
+    points: &[Correspondence],
+) -> Result<ExtrinsicCalibration, GError> {
+    if points.len() < MIN_POINTS {
+        return Err(GError::CalibrationError).attach_printable(format!(
+            "Need at least {MIN_POINTS} points, got {}",
+            points.len()
+        ));
+    }
+
+    let world: Vec<Vector3<f64>> = points.iter().map(|p| to_na(p.world)).collect();
+    let bearings: Vec<Vector3<f64>> = points
+        .iter()
+        .map(|p| {
+            let coords =
+                ImageCoords::new(p.pixel.x, p.pixel.y, camera.img_width, camera.img_height);
+            to_na(calc_local_dir_vec(camera, &coords))
+        })
+        .collect();
+
+    let residuals = |x: &DVector<f64>| {
+        let rot = Rotation3::new(Vector3::new(x[0], x[1], x[2]));
+        let pos = Vector3::new(x[3], x[4], x[5]);
+
+        let r = world.iter().zip(&bearings).flat_map(|(p, b)| {
+            let local = rot.inverse_transform_vector(&(p - pos));
+            (local.normalize() - b).data.0[0]
+        });
+
+        DVector::from_iterator(3 * world.len(), r)
+    };
+
+    let configured = {
+        let q = camera.quat();
+        let axis = UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(
+            q.w as f64, q.x as f64, q.y as f64, q.z as f64,
+        ))
+        .scaled_axis();
+        let pos = to_na(*camera.pos());
+        DVector::from_vec(vec![axis.x, axis.y, axis.z, pos.x, pos.y, pos.z])
+    };
+
+    let best = std::iter::once(configured)
+        .chain(dlt_pose(&world, &bearings))
+        .chain(planar_pose(&world, &bearings))
+        .map(|x0| levenberg_marquardt(residuals, x0))
+        .min_by(|a, b| {
+            residuals(a)
+                .norm_squared()
+                .total_cmp(&residuals(b).norm_squared())
+        })
+        .expect("at least one starting point");
+
+    let rms_angle = (residuals(&best).norm_squared() / world.len() as f64).sqrt();
+    if rms_angle.is_nan() || rms_angle > MAX_RMS_ANGLE {
+        return Err(GError::CalibrationError).attach_printable(format!(
+            "No starting pose converged, the best is off by {:.1} degrees RMS, check the points",
+            rms_angle.to_degrees()
+        ));
+    }
+
+    let rot = UnitQuaternion::from_scaled_axis(Vector3::new(best[0], best[1], best[2]));
+    let quat = Quat::from_xyzw(rot.i as f32, rot.j as f32, rot.k as f32, rot.w as f32);
+    let pos = Vec3A::new(best[3] as f32, best[4] as f32, best[5] as f32);
+
+    let residuals = points
+        .iter()
+        .zip(&bearings)
+        .map(|(p, b)| {
+            let local = quat.inverse().mul_vec3a(p.world - pos);
+            let observed = Vec3A::new(b.x as f32, b.y as f32, b.z as f32);
+
+            PointResidual {
+                name: p.name.clone(),
+                pixels: project_local_dir(camera, local).map(|px| px.distance(p.pixel)),
+                angle: local.angle_between(observed),
+            }
+        })
+        .collect();
+
+    Ok(ExtrinsicCalibration {
+        pos,
+        quat,
+        residuals,
+    })
+}
+
+/// Linear pose from at least six non-coplanar points, as
+/// `[rotation vector, position]` of the camera.
+fn dlt_pose(world: &[Vector3<f64>], bearings: &[Vector3<f64>]) -> Option<DVector<f64>> {
+    if world.len() < DLT_MIN_POINTS {
+        return None;
+    }
+
+    // condition the world points around their centroid
+    let n = world.len() as f64;
+    let centroid = world.iter().sum::<Vector3<f64>>() / n;
+    let scale = world.iter().map(|p| (p - centroid).norm()).sum::<f64>() / n;
+    let scale = scale.max(f64::EPSILON);
+
+    let mut a = DMatrix::zeros(2 * world.len(), 12);
+    for (i, (p, b)) in world.iter().zip(bearings).enumerate() {
+        let p = (p - centroid) / scale;
+        let ph = [p.x, p.y, p.z, 1.0];
+        // local ∝ b, so b.x * local.y - b.y * local.x = 0 and likewise for z
+        for k in 0..4 {
+            a[(2 * i, k)] = -b.y * ph[k];
+            a[(2 * i, 4 + k)] = b.x * ph[k];
+            a[(2 * i + 1, k)] = -b.z * ph[k];
+            a[(2 * i + 1, 8 + k)] = b.x * ph[k];
+        }
+    }
+
+    let mut m = Matrix3x4::from_row_slice(null_vector(&a).as_slice());
+
+    // points have to end up in front of the camera
+    let in_front = world
+        .iter()
+        .filter(|p| (m * ((*p - centroid) / scale).push(1.0)).x > 0.0)
+        .count();
+    if in_front * 2 < world.len() {
+        m = -m;
+    }
+
+    let svd = m.fixed_view::<3, 3>(0, 0).into_owned().svd(true, true);
+    let (u, v_t) = (svd.u?, svd.v_t?);
+    let s = svd.singular_values.mean();
+    let rt: Matrix3<f64> = u * v_t;
+    if rt.determinant() < 0.0 {
+        return None;
+    }
+
+    // local = Rᵀ (p - pos), with p in conditioned coordinates
+    let rot = Rotation3::from_matrix_unchecked(rt.transpose());
+    let t = m.column(3) / s;
+    let pos = -(rot * t) * scale + centroid;
+    let axis = rot.scaled_axis();
+
+    Some(DVector::from_vec(vec![
+        axis.x, axis.y, axis.z, pos.x, pos.y, pos.z,
+    ]))
+}
+
+/// Pose from the homography between the best fitting plane through the points
+/// and the camera, as `[rotation vector, position]` of the camera. Exact for
+/// points on a floor or wall, a rough start otherwise.
+fn planar_pose(world: &[Vector3<f64>], bearings: &[Vector3<f64>]) -> Option<DVector<f64>> {
+    if world.len() < MIN_POINTS {
+        return None;
+    }
+
+    let n = world.len() as f64;
+    let centroid = world.iter().sum::<Vector3<f64>>() / n;
+    let scale = world.iter().map(|p| (p - centroid).norm()).sum::<f64>() / n;
+    let scale = scale.max(f64::EPSILON);
+
+    // the plane is spanned by the two largest principal directions
+    let spread = world
+        .iter()
+        .map(|p| (p - centroid) * (p - centroid).transpose())
+        .sum::<Matrix3<f64>>();
+    let eigen = spread.symmetric_eigen();
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+    let e1: Vector3<f64> = eigen.eigenvectors.column(order[0]).into_owned();
+    let e2: Vector3<f64> = eigen.eigenvectors.column(order[1]).into_owned();
+    let plane = Matrix3::from_columns(&[e1, e2, e1.cross(&e2)]);
+
+    // local = h (q.x, q.y, 1) with q the conditioned plane coordinates
+    let mut a = DMatrix::zeros(2 * world.len(), 9);
+    for (i, (p, b)) in world.iter().zip(bearings).enumerate() {
+        let d = (p - centroid) / scale;
+        let qh = [d.dot(&e1), d.dot(&e2), 1.0];
+        for k in 0..3 {
+            a[(2 * i, k)] = -b.y * qh[k];
+            a[(2 * i, 3 + k)] = b.x * qh[k];
+            a[(2 * i + 1, k)] = -b.z * qh[k];
+            a[(2 * i + 1, 6 + k)] = b.x * qh[k];
+        }
+    }
+
+    let mut h = Matrix3::from_row_slice(null_vector(&a).as_slice());
+
+    // points have to end up in front of the camera
+    let in_front = world
+        .iter()
+        .zip(bearings)
+        .filter(|(p, b)| {
+            let d = (*p - centroid) / scale;
+            (h * Vector3::new(d.dot(&e1), d.dot(&e2), 1.0)).dot(b) > 0.0
+        })
+        .count();
+    if in_front * 2 < world.len() {
+        h = -h;
+    }
+
+    // the first two columns are the plane axes in the camera frame
+    let k = (h.column(0).norm() + h.column(1).norm()) / 2.0;
+    if k < f64::EPSILON {
+        return None;
+    }
+    let (c1, c2) = (h.column(0) / k, h.column(1) / k);
+    let local_axes = Matrix3::from_columns(&[c1, c2, c1.cross(&c2)]);
+    let svd = local_axes.svd(true, true);
+    let rt = svd.u? * svd.v_t? * plane.transpose();
+    if rt.determinant() < 0.0 {
+        return None;
+    }
+
+    // local = Rᵀ (p - pos), and the third column is Rᵀ (centroid - pos)
+    let rot = Rotation3::from_matrix(&rt.transpose());
+    let pos = centroid - rot * (h.column(2) / k) * scale;
+    let axis = rot.scaled_axis();
+
+    Some(DVector::from_vec(vec![
+        axis.x, axis.y, axis.z, pos.x, pos.y, pos.z,
+    ]))
+}
+
+fn to_na(v: Vec3A) -> Vector3<f64> {
+    Vector3::new(v.x as f64, v.y as f64, v.z as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic(camera: &CameraProperties, world: &[Vec3A]) -> Vec<Correspondence> {
+        world
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let local = camera.quat().inverse().mul_vec3a(*p - *camera.pos());
+                Correspondence {
+                    name: format!("p{i}"),
+                    world: *p,
+                    pixel: project_local_dir(camera, local).unwrap(),
+                }
+            })
+            .collect()
+    }
+
+    fn truth() -> CameraProperties {
+        let mut camera = CameraProperties::test_new();
+        camera.pos_x = -1.0;
+        camera.pos_y = 0.5;
+        camera.pos_z = 2.2;
+        camera.yaw = 0.3;
+        camera.pitch = 0.25;
+        camera.roll = -0.05;
+        camera
+    }
+
+    fn guess() -> CameraProperties {
+        let mut camera = CameraProperties::test_new();
+        camera.pos_x = -0.8;
+        camera.pos_y = 0.6;
+        camera.pos_z = 2.0;
+        camera.yaw = 0.2;
+        camera.pitch = 0.1;
+        camera
+    }
+
+    fn assert_recovered(result: &ExtrinsicCalibration) {
+        let camera = truth();
+        assert!(
+            result.pos.abs_diff_eq(*camera.pos(), 1e-3),
+            "{}",
+            result.pos
+        );
+        assert!(result.quat.angle_between(camera.quat()) < 1e-3);
+        assert!(result.rms() < 0.1, "rms {}", result.rms());
+        assert!(result.residuals.iter().all(|r| r.angle < 1e-3));
+    }
+
+    #[test]
+    fn solves_pose_from_spread_points() {
+        let world = [
+            Vec3A::new(3.0, 1.0, 0.0),
+            Vec3A::new(3.5, 2.5, 1.0),
+            Vec3A::new(4.0, -0.5, 1.5),
+            Vec3A::new(2.5, 1.5, 2.0),
+            Vec3A::new(3.2, 0.0, 0.5),
+            Vec3A::new(4.5, 2.0, 0.2),
+            Vec3A::new(2.8, 2.2, 1.4),
+        ];
+        let points = synthetic(&truth(), &world);
+
+        // a wildly wrong configured pose only leaves the dlt to start from
+        let mut wrong = guess();
+        wrong.yaw = 3.0;
+        assert_recovered(&calibrate_extrinsics(&wrong, &points).unwrap());
+    }
+
+    #[test]
+    fn solves_pose_from_floor_points() {
+        let world = [
+            Vec3A::new(3.0, 1.0, 0.0),
+            Vec3A::new(3.5, 2.5, 0.0),
+            Vec3A::new(4.0, -0.5, 0.0),
+            Vec3A::new(2.5, 1.5, 0.0),
+            Vec3A::new(4.5, 2.0, 0.0),
+        ];
+        let points = synthetic(&truth(), &world);
+
+        assert_recovered(&calibrate_extrinsics(&guess(), &points).unwrap());
+
+        // too few points and all on one plane for the dlt, the homography
+        // still finds the pose from a wildly wrong configured one
+        let mut wrong = guess();
+        wrong.yaw = 3.0;
+        assert_recovered(&calibrate_extrinsics(&wrong, &points).unwrap());
+        assert_recovered(&calibrate_extrinsics(&wrong, &points[..4]).unwrap());
+    }
+
+    #[test]
+    fn fails_when_no_pose_fits() {
+        let world = [
+            Vec3A::new(3.0, 1.0, 0.0),
+            Vec3A::new(3.5, 2.5, 1.0),
+            Vec3A::new(4.0, -0.5, 1.5),
+            Vec3A::new(2.5, 1.5, 2.0),
+            Vec3A::new(3.2, 0.0, 0.5),
+            Vec3A::new(4.5, 2.0, 0.2),
+        ];
+        let mut points = synthetic(&truth(), &world);
+
+        // pixels swapped between points can't come from any pose
+        let pixel = points[0].pixel;
+        points[0].pixel = points[3].pixel;
+        points[3].pixel = pixel;
+        let pixel = points[1].pixel;
+        points[1].pixel = points[5].pixel;
+        points[5].pixel = pixel;
+
+        assert!(calibrate_extrinsics(&guess(), &points).is_err());
+    }
+
+    #[test]
+    fn parses_survey() {
+        let survey: Survey = toml::from_str(
+            r#"
+            [[points]]
+            name = "door"
+            x = 1
+            y = 2
+            z = 0
+            camera1 = [10.0, 20.0]
+
+            [[points]]
+            x = 0
+            y = 0
+            z = 0
+            camera1 = [1.0, 2.0]
+            camera2 = [3.0, 4.0]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(survey.correspondences("camera1").len(), 2);
+        let cam2 = survey.correspondences("camera2");
+        assert_eq!(cam2.len(), 1);
+        assert_eq!(cam2[0].name, "#1");
+        assert_eq!(cam2[0].pixel, Vec2::new(3.0, 4.0));
+    }
+}
@@ -0,0 +1,426 @@
+use error_stack::{Result, ResultExt};
+use glam::Vec2;
+use nalgebra::{DMatrix, DVector, Matrix3, Rotation3, Vector3};
+
+use super::lm::{levenberg_marquardt, null_vector};
+use crate::{
+    config::{Distortion, Intrinsics},
+    GError,
+};
+
+/// Distortion model to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LensModel {
+    BrownConrady,
+    Fisheye,
+}
+
+impl LensModel {
+    fn coefficients(&self) -> usize {
+        match self {
+            Self::BrownConrady => 5,
+            Self::Fisheye => 4,
+        }
+    }
+
+    fn distortion(&self, d: &[f64]) -> Distortion {
+        match self {
+            Self::BrownConrady => Distortion::BrownConrady {
+                k1: d[0] as f32,
+                k2: d[1] as f32,
+                p1: d[2] as f32,
+                p2: d[3] as f32,
+                k3: d[4] as f32,
+            },
+            Self::Fisheye => Distortion::Fisheye {
+                k1: d[0] as f32,
+                k2: d[1] as f32,
+                k3: d[2] as f32,
+                k4: d[3] as f32,
+            },
+        }
+    }
+
+    /// Double precision version of [`Distortion::distort`], the refinement
+    /// needs more precision than `f32` for its finite differences.
+    fn distort(&self, d: &[f64], x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Self::BrownConrady => {
+                let (k1, k2, p1, p2, k3) = (d[0], d[1], d[2], d[3], d[4]);
+                let r2 = x * x + y * y;
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+
+                (
+                    x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+                    y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+                )
+            }
+            Self::Fisheye => {
+                let r = (x * x + y * y).sqrt();
+                if r < f64::EPSILON {
+                    return (x, y);
+                }
+                let theta = r.atan();
+                let t2 = theta * theta;
+                let theta_d = theta * (1.0 + t2 * (d[0] + t2 * (d[1] + t2 * (d[2] + t2 * d[3]))));
+
+                (x * theta_d / r, y * theta_d / r)
+            }
+        }
+    }
+}
+
+/// Result of an intrinsic calibration.
+#[derive(Debug, Clone)]
+pub struct IntrinsicCalibration {
+    pub intrinsics: Intrinsics,
+    pub distortion: Distortion,
+    /// Size of the images the calibration was done on.
+    pub image_size: (u32, u32),
+    /// RMS reprojection error over all corners, in pixels.
+    pub rms: f64,
+    /// RMS reprojection error of each view, in pixels.
+    pub per_view_rms: Vec<f64>,
+}
+
+impl IntrinsicCalibration {
+    /// Intrinsics rescaled to a different image resolution.
+    pub fn intrinsics_for(&self, width: u32, height: u32) -> Intrinsics {
+        let sx = width as f32 / self.image_size.0 as f32;
+        let sy = height as f32 / self.image_size.1 as f32;
+
+        Intrinsics {
+            fx: self.intrinsics.fx * sx,
+            fy: self.intrinsics.fy * sy,
+            cx: self.intrinsics.cx * sx,
+            cy: self.intrinsics.cy * sy,
+        }
+    }
+}
+
+/// Calibrates a camera from views of a planar target. `object` holds the target
+/// points on the `z = 0` plane and each view the matching pixel positions.
+pub fn calibrate_intrinsics(
+    object: &[Vec2],
+    views: &[Vec<Vec2>],
+    image_size: (u32, u32),
+    model: LensModel,
+) -> Result<IntrinsicCalibration, GError> {
+    if views.len() < 3 {
+        return Err(GError::CalibrationError)
+            .attach_printable(format!("Need at least 3 views, got {}", views.len()));
+    }
+
+    let homographies = views
+        .iter()
+        .map(|view| homography(object, view))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(GError::CalibrationError)
+        .attach_printable("Couldn't estimate a homography for every view")?;
+
+    let k = initial_intrinsics(&homographies, image_size)
+        .ok_or(GError::CalibrationError)
+        .attach_printable("The views don't constrain the intrinsics, vary the board pose more")?;
+
+    let n_dist = model.coefficients();
+    let mut x0 = vec![k[(0, 0)], k[(1, 1)], k[(0, 2)], k[(1, 2)]];
+    x0.extend(std::iter::repeat_n(0.0, n_dist));
+    for h in &homographies {
+        let (rvec, t) = pose_from_homography(&k, h);
+        x0.extend(rvec.iter().chain(t.iter()));
+    }
+
+    let residuals = |p: &DVector<f64>| {
+        let dist = &p.as_slice()[4..4 + n_dist];
+        let mut r = Vec::with_capacity(2 * object.len() * views.len());
+
+        for (i, view) in views.iter().enumerate() {
+            let pose = &p.as_slice()[4 + n_dist + 6 * i..4 + n_dist + 6 * (i + 1)];
+            let rot = Rotation3::new(Vector3::new(pose[0], pose[1], pose[2]));
+            let t = Vector3::new(pose[3], pose[4], pose[5]);
+
+            for (obj, px) in object.iter().zip(view) {
+                let pc = rot * Vector3::new(obj.x as f64, obj.y as f64, 0.0) + t;
+                let (xd, yd) = model.distort(dist, pc.x / pc.z, pc.y / pc.z);
+
+                r.push(p[0] * xd + p[2] - px.x as f64);
+                r.push(p[1] * yd + p[3] - px.y as f64);
+            }
+        }
+
+        DVector::from_vec(r)
+    };
+
+    let x = levenberg_marquardt(residuals, DVector::from_vec(x0));
+    let r = residuals(&x);
+
+    let per_view_rms: Vec<f64> = r
+        .as_slice()
+        .chunks(2 * object.len())
+        .map(|c| (c.iter().map(|v| v * v).sum::<f64>() / object.len() as f64).sqrt())
+        .collect();
+    let rms = (r.norm_squared() / (object.len() * views.len()) as f64).sqrt();
+
+    if !rms.is_finite() {
+        return Err(GError::CalibrationError).attach_printable("Refinement diverged");
+    }
+
+    Ok(IntrinsicCalibration {
+        intrinsics: Intrinsics {
+            fx: x[0] as f32,
+            fy: x[1] as f32,
+            cx: x[2] as f32,
+            cy: x[3] as f32,
+        },
+        distortion: model.distortion(&x.as_slice()[4..4 + n_dist]),
+        image_size,
+        rms,
+        per_view_rms,
+    })
+}
+
+/// Similarity transform moving `points` to their centroid with mean distance √2.
+fn normalisation(points: impl Iterator<Item = (f64, f64)> + Clone) -> Matrix3<f64> {
+    let n = points.clone().count() as f64;
+    let (mx, my) = points
+        .clone()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+    let spread = points
+        .map(|p| ((p.0 - mx).powi(2) + (p.1 - my).powi(2)).sqrt())
+        .sum::<f64>()
+        / n;
+    let s = std::f64::consts::SQRT_2 / spread.max(f64::EPSILON);
+
+    Matrix3::new(s, 0.0, -s * mx, 0.0, s, -s * my, 0.0, 0.0, 1.0)
+}
+
+/// Normalised DLT homography taking `src` to `dst`.
+pub(crate) fn homography(src: &[Vec2], dst: &[Vec2]) -> Option<Matrix3<f64>> {
+    if src.len() < 4 || src.len() != dst.len() {
+        return None;
+    }
+
+    let ts = normalisation(src.iter().map(|p| (p.x as f64, p.y as f64)));
+    let td = normalisation(dst.iter().map(|p| (p.x as f64, p.y as f64)));
+
+    let mut a = DMatrix::zeros(2 * src.len(), 9);
+    for (i, (s, d)) in src.iter().zip(dst).enumerate() {
+        let s = ts * Vector3::new(s.x as f64, s.y as f64, 1.0);
+        let d = td * Vector3::new(d.x as f64, d.y as f64, 1.0);
+        let (x, y, u, v) = (s.x, s.y, d.x, d.y);
+
+        a.row_mut(2 * i)
+            .copy_from_slice(&[-x, -y, -1.0, 0.0, 0.0, 0.0, u * x, u * y, u]);
+        a.row_mut(2 * i + 1)
+            .copy_from_slice(&[0.0, 0.0, 0.0, -x, -y, -1.0, v * x, v * y, v]);
+    }
+
+    let h = null_vector(&a);
+    let hn = Matrix3::from_row_slice(h.as_slice());
+    let h = td.try_inverse()? * hn * ts;
+
+    (h[(2, 2)].abs() > f64::EPSILON).then(|| h / h[(2, 2)])
+}
+
+/// Zhang's closed form intrinsics, assuming zero skew.
+fn initial_intrinsics(
+    homographies: &[Matrix3<f64>],
+    image_size: (u32, u32),
+) -> Option<Matrix3<f64>> {
+    // work in normalised pixels to keep B well conditioned
+    let s = image_size.0.max(image_size.1) as f64;
+    let n = Matrix3::new(
+        1.0 / s,
+        0.0,
+        -(image_size.0 as f64) / (2.0 * s),
+        0.0,
+        1.0 / s,
+        -(image_size.1 as f64) / (2.0 * s),
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    let v = |h: &Matrix3<f64>, i: usize, j: usize| {
+        [
+            h[(0, i)] * h[(0, j)],
+            h[(0, i)] * h[(1, j)] + h[(1, i)] * h[(0, j)],
+            h[(1, i)] * h[(1, j)],
+            h[(2, i)] * h[(0, j)] + h[(0, i)] * h[(2, j)],
+            h[(2, i)] * h[(1, j)] + h[(1, i)] * h[(2, j)],
+            h[(2, i)] * h[(2, j)],
+        ]
+    };
+
+    let mut a = DMatrix::zeros(2 * homographies.len() + 1, 6);
+    for (k, h) in homographies.iter().enumerate() {
+        let h = n * h;
+        let (v12, v11, v22) = (v(&h, 0, 1), v(&h, 0, 0), v(&h, 1, 1));
+
+        a.row_mut(2 * k).copy_from_slice(&v12);
+        for c in 0..6 {
+            a[(2 * k + 1, c)] = v11[c] - v22[c];
+        }
+    }
+    // zero skew
+    a[(2 * homographies.len(), 1)] = 1.0;
+
+    let mut b = null_vector(&a);
+    if b[0] < 0.0 {
+        b = -b;
+    }
+    let (b11, b12, b22, b13, b23, b33) = (b[0], b[1], b[2], b[3], b[4], b[5]);
+
+    let denom = b11 * b22 - b12 * b12;
+    if denom <= 0.0 {
+        return None;
+    }
+
+    let v0 = (b12 * b13 - b11 * b23) / denom;
+    let lambda = b33 - (b13 * b13 + v0 * (b12 * b13 - b11 * b23)) / b11;
+    if lambda / b11 <= 0.0 {
+        return None;
+    }
+
+    let alpha = (lambda / b11).sqrt();
+    let beta = (lambda * b11 / denom).sqrt();
+    let u0 = -b13 * alpha * alpha / lambda;
+
+    let k = Matrix3::new(alpha, 0.0, u0, 0.0, beta, v0, 0.0, 0.0, 1.0);
+
+    n.try_inverse().map(|n_inv| n_inv * k)
+}
+
+/// Target pose from its homography, as a rotation vector and translation.
+pub(crate) fn pose_from_homography(
+    k: &Matrix3<f64>,
+    h: &Matrix3<f64>,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let k_inv = k.try_inverse().unwrap_or_else(Matrix3::identity);
+    let h1 = k_inv * h.column(0);
+    let h2 = k_inv * h.column(1);
+    let h3 = k_inv * h.column(2);
+
+    let mut lambda = 1.0 / h1.norm();
+    // the target has to be in front of the camera
+    if h3.z * lambda < 0.0 {
+        lambda = -lambda;
+    }
+
+    let r1 = h1 * lambda;
+    let r2 = h2 * lambda;
+    let t = h3 * lambda;
+    let rot = Rotation3::from_matrix(&Matrix3::from_columns(&[r1, r2, r1.cross(&r2)]));
+
+    (rot.scaled_axis(), t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(
+        k: &Intrinsics,
+        d: &[f64],
+        model: LensModel,
+        rot: &Rotation3<f64>,
+        t: &Vector3<f64>,
+        p: Vec2,
+    ) -> Vec2 {
+        let pc = rot * Vector3::new(p.x as f64, p.y as f64, 0.0) + t;
+        let (x, y) = model.distort(d, pc.x / pc.z, pc.y / pc.z);
+        Vec2::new(
+            (k.fx as f64 * x + k.cx as f64) as f32,
+            (k.fy as f64 * y + k.cy as f64) as f32,
+        )
+    }
+
+    fn synthetic_views(k: &Intrinsics, d: &[f64], model: LensModel) -> (Vec<Vec2>, Vec<Vec<Vec2>>) {
+        let object: Vec<Vec2> = (0..6)
+            .flat_map(|j| (0..8).map(move |i| Vec2::new(i as f32 * 0.03, j as f32 * 0.03)))
+            .collect();
+
+        let poses = [
+            (
+                Vector3::new(0.1, -0.2, 0.05),
+                Vector3::new(-0.1, -0.08, 0.5),
+            ),
+            (
+                Vector3::new(-0.3, 0.1, 0.0),
+                Vector3::new(-0.12, -0.05, 0.45),
+            ),
+            (
+                Vector3::new(0.25, 0.3, -0.1),
+                Vector3::new(-0.05, -0.1, 0.55),
+            ),
+            (
+                Vector3::new(0.0, -0.35, 0.2),
+                Vector3::new(-0.1, -0.06, 0.6),
+            ),
+            (
+                Vector3::new(0.35, 0.05, 0.3),
+                Vector3::new(-0.08, -0.12, 0.5),
+            ),
+            (
+                Vector3::new(-0.2, -0.25, -0.2),
+                Vector3::new(-0.15, -0.05, 0.52),
+            ),
+        ];
+
+        let views = poses
+            .iter()
+            .map(|(r, t)| {
+                let rot = Rotation3::new(*r);
+                object
+                    .iter()
+                    .map(|p| project(k, d, model, &rot, t, *p))
+                    .collect()
+            })
+            .collect();
+
+        (object, views)
+    }
+
+    #[test]
+    fn recovers_brown_conrady() {
+        let k = Intrinsics {
+            fx: 800.0,
+            fy: 780.0,
+            cx: 330.0,
+            cy: 245.0,
+        };
+        let d = [-0.2, 0.05, 0.001, -0.0005, 0.0];
+        let (object, views) = synthetic_views(&k, &d, LensModel::BrownConrady);
+
+        let calib =
+            calibrate_intrinsics(&object, &views, (640, 480), LensModel::BrownConrady).unwrap();
+
+        assert!(calib.rms < 1e-2, "rms {}", calib.rms);
+        assert!((calib.intrinsics.fx - k.fx).abs() < 1.0);
+        assert!((calib.intrinsics.fy - k.fy).abs() < 1.0);
+        assert!((calib.intrinsics.cx - k.cx).abs() < 1.0);
+        assert!((calib.intrinsics.cy - k.cy).abs() < 1.0);
+        let Distortion::BrownConrady { k1, .. } = calib.distortion else {
+            panic!("wrong model");
+        };
+        assert!((k1 + 0.2).abs() < 1e-2);
+    }
+
+    #[test]
+    fn recovers_fisheye() {
+        let k = Intrinsics {
+            fx: 500.0,
+            fy: 500.0,
+            cx: 320.0,
+            cy: 240.0,
+        };
+        let d = [0.05, -0.02, 0.0, 0.0];
+        let (object, views) = synthetic_views(&k, &d, LensModel::Fisheye);
+
+        let calib = calibrate_intrinsics(&object, &views, (640, 480), LensModel::Fisheye).unwrap();
+
+        assert!(calib.rms < 1e-2, "rms {}", calib.rms);
+        assert!((calib.intrinsics.fx - k.fx).abs() < 1.0);
+        assert!((calib.intrinsics.cy - k.cy).abs() < 1.0);
+    }
+}
@@ -0,0 +1,114 @@
+use nalgebra::{DMatrix, DVector};
+
+const MAX_ITERATIONS: usize = 100;
+const INITIAL_DAMPING: f64 = 1e-3;
+const MIN_RELATIVE_IMPROVEMENT: f64 = 1e-10;
+
+/// Minimises the squared norm of `residuals` with Levenberg-Marquardt, using a
+/// forward difference jacobian.
+pub(crate) fn levenberg_marquardt<F>(residuals: F, x0: DVector<f64>) -> DVector<f64>
+where
+    F: Fn(&DVector<f64>) -> DVector<f64>,
+{
+    let mut x = x0;
+    let mut r = residuals(&x);
+    let mut cost = r.norm_squared();
+    let mut damping = INITIAL_DAMPING;
+
+    for _ in 0..MAX_ITERATIONS {
+        let j = jacobian(&residuals, &x, &r);
+        let jtj = j.transpose() * &j;
+        let jtr = j.transpose() * &r;
+
+        let mut improved = false;
+        while damping < 1e12 {
+            let mut a = jtj.clone();
+            for i in 0..a.nrows() {
+                a[(i, i)] += damping * jtj[(i, i)].max(1e-9);
+            }
+
+            let Some(step) = a.cholesky().map(|c| c.solve(&(-&jtr))) else {
+                damping *= 10.0;
+                continue;
+            };
+
+            let candidate = &x + &step;
+            let candidate_r = residuals(&candidate);
+            let candidate_cost = candidate_r.norm_squared();
+
+            if candidate_cost.is_finite() && candidate_cost < cost {
+                let relative = (cost - candidate_cost) / cost.max(f64::MIN_POSITIVE);
+                x = candidate;
+                r = candidate_r;
+                cost = candidate_cost;
+                damping = (damping / 10.0).max(1e-12);
+                improved = relative > MIN_RELATIVE_IMPROVEMENT;
+                break;
+            }
+
+            damping *= 10.0;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    x
+}
+
+fn jacobian<F>(residuals: &F, x: &DVector<f64>, r: &DVector<f64>) -> DMatrix<f64>
+where
+    F: Fn(&DVector<f64>) -> DVector<f64>,
+{
+    let mut j = DMatrix::zeros(r.len(), x.len());
+    let mut probe = x.clone();
+
+    for i in 0..x.len() {
+        let h = 1e-7 * x[i].abs().max(1.0);
+        probe[i] = x[i] + h;
+        let column = (residuals(&probe) - r) / h;
+        j.set_column(i, &column);
+        probe[i] = x[i];
+    }
+
+    j
+}
+
+/// Unit vector minimising `|a x|`.
+pub(crate) fn null_vector(a: &DMatrix<f64>) -> DVector<f64> {
+    let eigen = (a.transpose() * a).symmetric_eigen();
+    let (min, _) = eigen
+        .eigenvalues
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .expect("empty matrix");
+
+    eigen.eigenvectors.column(min).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_exponential() {
+        // y = 2 exp(-0.5 x)
+        let xs: Vec<f64> = (0..20).map(|i| i as f64 * 0.25).collect();
+        let ys: Vec<f64> = xs.iter().map(|x| 2.0 * (-0.5 * x).exp()).collect();
+
+        let fit = levenberg_marquardt(
+            |p| {
+                DVector::from_iterator(
+                    xs.len(),
+                    xs.iter().zip(&ys).map(|(x, y)| p[0] * (p[1] * x).exp() - y),
+                )
+            },
+            DVector::from_vec(vec![1.0, 0.0]),
+        );
+
+        assert!((fit[0] - 2.0).abs() < 1e-6);
+        assert!((fit[1] + 0.5).abs() < 1e-6);
+    }
+}
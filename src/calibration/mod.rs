@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use error_stack::{Result, ResultExt};
+
+use crate::{imgproc::GrayImage, GError};
+
+mod checkerboard;
+mod extrinsics;
+mod intrinsics;
+mod lm;
+
+pub use checkerboard::{find_corners, Board};
+pub use extrinsics::{
+    calibrate_extrinsics, Correspondence, ExtrinsicCalibration, PointResidual, Survey, SurveyPoint,
+};
+pub use intrinsics::{calibrate_intrinsics, IntrinsicCalibration, LensModel};
+
+pub(crate) use intrinsics::homography;
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+/// Intrinsic calibration from a directory of checkerboard images.
+pub struct DirCalibration {
+    pub calibration: IntrinsicCalibration,
+    /// Images the board was found in, in the order of `per_view_rms`.
+    pub used: Vec<PathBuf>,
+    /// Images the board wasn't found in.
+    pub skipped: Vec<PathBuf>,
+}
+
+pub fn calibrate_dir(
+    dir: &Path,
+    board: &Board,
+    model: LensModel,
+) -> Result<DirCalibration, GError> {
+    if board.cols < 2 || board.rows < 2 {
+        return Err(GError::CalibrationError).attach_printable(format!(
+            "A {}x{} board is too small, it needs at least 2x2 inner corners",
+            board.cols, board.rows
+        ));
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .change_context(GError::CalibrationError)
+        .attach_printable_lazy(|| format!("Couldn't read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+
+    let mut image_size = None;
+    let mut views = vec![];
+    let mut used = vec![];
+    let mut skipped = vec![];
+
+    for path in paths {
+        let img = GrayImage::open(&path).change_context(GError::CalibrationError)?;
+        let size = (img.width() as u32, img.height() as u32);
+
+        if *image_size.get_or_insert(size) != size {
+            return Err(GError::CalibrationError).attach_printable(format!(
+                "{} is {}x{}, the other images aren't",
+                path.display(),
+                size.0,
+                size.1
+            ));
+        }
+
+        match find_corners(&img, board) {
+            Some(corners) => {
+                views.push(corners);
+                used.push(path);
+            }
+            None => skipped.push(path),
+        }
+    }
+
+    let image_size = image_size
+        .ok_or(GError::CalibrationError)
+        .attach_printable_lazy(|| format!("No images found in {}", dir.display()))?;
+
+    let calibration = calibrate_intrinsics(&board.object_points(), &views, image_size, model)?;
+
+    Ok(DirCalibration {
+        calibration,
+        used,
+        skipped,
+    })
+}
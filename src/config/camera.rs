@@ -1,12 +1,12 @@
 use std::sync::OnceLock;
 
 // TODO: Vec3A or Vec3
-use glam::{EulerRot, Quat, Vec3A};
+use glam::{EulerRot, Quat, Vec2, Vec3A};
 use serde::Deserialize;
 
 use crate::{HasGlamPosition, HasGlamQuat};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct CameraProperties {
     pub fov_x: f32,
     pub fov_y: f32,
@@ -18,6 +18,8 @@ pub struct CameraProperties {
     pub roll: f32,
     pub img_height: u32,
     pub img_width: u32,
+    pub intrinsics: Option<Intrinsics>,
+    pub distortion: Option<Distortion>,
     #[serde(skip)]
     quat: OnceLock<Quat>,
     #[serde(skip)]
@@ -32,19 +34,47 @@ impl CameraProperties {
             pos_x: 0.0,
             pos_y: 0.0,
             pos_z: 0.0,
-            fov_x: 1.0472, // 60 degrees
+            fov_x: std::f32::consts::FRAC_PI_3, // 60 degrees
             fov_y: 0.58905,
             pitch: 0.0,
             yaw: 0.0,
             roll: 0.0,
             img_height: 720,
             img_width: 1280,
+            intrinsics: None,
+            distortion: None,
             quat: OnceLock::new(),
             dir_vec: OnceLock::new(),
             pos: OnceLock::new(),
         }
     }
 
+    /// Two cameras 4 apart along y, turned towards each other to look at the
+    /// space in front of them along x.
+    #[cfg(test)]
+    pub fn test_stereo_pair() -> (Self, Self) {
+        (
+            Self::test_new().with_pose(Vec3A::ZERO, 0.3, 0.0, 0.0),
+            Self::test_new().with_pose(Vec3A::new(0.0, 4.0, 0.0), -0.5, 0.0, 0.0),
+        )
+    }
+
+    /// Copy of the camera moved to a new pose.
+    pub fn with_pose(&self, pos: Vec3A, yaw: f32, pitch: f32, roll: f32) -> Self {
+        Self {
+            pos_x: pos.x,
+            pos_y: pos.y,
+            pos_z: pos.z,
+            yaw,
+            pitch,
+            roll,
+            quat: OnceLock::new(),
+            dir_vec: OnceLock::new(),
+            pos: OnceLock::new(),
+            ..self.clone()
+        }
+    }
+
     pub fn direction_vector(&self) -> &Vec3A {
         self.dir_vec
             .get_or_init(|| self.quat().mul_vec3a(crate::math::BASE_FORWARD_VECTOR))
@@ -53,6 +83,153 @@ impl CameraProperties {
     pub fn forward_vector(&self) -> &Vec3A {
         &crate::math::BASE_FORWARD_VECTOR
     }
+
+    /// Pinhole intrinsics of the camera. Falls back to a centred principal point
+    /// with focal lengths derived from the field of view when none are configured.
+    pub fn intrinsics_or_fov(&self) -> Intrinsics {
+        self.intrinsics
+            .unwrap_or_else(|| Intrinsics::from_fov(self))
+    }
+
+    /// Whether pixels should be back-projected with the full pinhole model
+    /// instead of the FOV-only approximation.
+    pub fn has_lens_model(&self) -> bool {
+        self.intrinsics.is_some() || self.distortion.is_some()
+    }
+}
+
+/// Pinhole camera intrinsics in pixels.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Intrinsics {
+    pub fx: f32,
+    pub fy: f32,
+    pub cx: f32,
+    pub cy: f32,
+}
+
+impl Intrinsics {
+    pub fn from_fov(camera: &CameraProperties) -> Self {
+        let cx = camera.img_width as f32 / 2.0;
+        let cy = camera.img_height as f32 / 2.0;
+
+        Self {
+            fx: cx / (camera.fov_x / 2.0).tan(),
+            fy: cy / (camera.fov_y / 2.0).tan(),
+            cx,
+            cy,
+        }
+    }
+
+    /// Pixel coordinates to (distorted) normalised image coordinates.
+    pub fn normalise(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new((x - self.cx) / self.fx, (y - self.cy) / self.fy)
+    }
+
+    /// (Distorted) normalised image coordinates to pixel coordinates.
+    pub fn denormalise(&self, p: Vec2) -> Vec2 {
+        Vec2::new(p.x * self.fx + self.cx, p.y * self.fy + self.cy)
+    }
+}
+
+/// Lens distortion coefficients, in the OpenCV conventions.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Distortion {
+    BrownConrady {
+        k1: f32,
+        k2: f32,
+        #[serde(default)]
+        p1: f32,
+        #[serde(default)]
+        p2: f32,
+        #[serde(default)]
+        k3: f32,
+    },
+    Fisheye {
+        k1: f32,
+        k2: f32,
+        #[serde(default)]
+        k3: f32,
+        #[serde(default)]
+        k4: f32,
+    },
+}
+
+impl Distortion {
+    const UNDISTORT_ITERATIONS: usize = 20;
+
+    /// Applies the distortion to undistorted normalised image coordinates.
+    pub fn distort(&self, p: Vec2) -> Vec2 {
+        match *self {
+            Self::BrownConrady { k1, k2, p1, p2, k3 } => {
+                let r2 = p.length_squared();
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                let xy = p.x * p.y;
+
+                Vec2::new(
+                    p.x * radial + 2.0 * p1 * xy + p2 * (r2 + 2.0 * p.x * p.x),
+                    p.y * radial + p1 * (r2 + 2.0 * p.y * p.y) + 2.0 * p2 * xy,
+                )
+            }
+            Self::Fisheye { .. } => {
+                let r = p.length();
+                if r < f32::EPSILON {
+                    return p;
+                }
+                let theta = r.atan();
+
+                p * (self.fisheye_theta_d(theta) / r)
+            }
+        }
+    }
+
+    /// Removes the distortion from distorted normalised image coordinates.
+    pub fn undistort(&self, p: Vec2) -> Vec2 {
+        match *self {
+            Self::BrownConrady { k1, k2, p1, p2, k3 } => {
+                let mut u = p;
+                for _ in 0..Self::UNDISTORT_ITERATIONS {
+                    let r2 = u.length_squared();
+                    let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                    let xy = u.x * u.y;
+                    let delta = Vec2::new(
+                        2.0 * p1 * xy + p2 * (r2 + 2.0 * u.x * u.x),
+                        p1 * (r2 + 2.0 * u.y * u.y) + 2.0 * p2 * xy,
+                    );
+                    u = (p - delta) / radial;
+                }
+                u
+            }
+            Self::Fisheye { k1, k2, k3, k4 } => {
+                let theta_d = p.length();
+                if theta_d < f32::EPSILON {
+                    return p;
+                }
+
+                // newton's method on theta_d = theta * (1 + k1 theta^2 + ...)
+                let mut theta = theta_d;
+                for _ in 0..Self::UNDISTORT_ITERATIONS {
+                    let t2 = theta * theta;
+                    let f = self.fisheye_theta_d(theta) - theta_d;
+                    let df =
+                        1.0 + t2 * (3.0 * k1 + t2 * (5.0 * k2 + t2 * (7.0 * k3 + t2 * 9.0 * k4)));
+                    theta -= f / df;
+                }
+
+                p * (theta.tan() / theta_d)
+            }
+        }
+    }
+
+    fn fisheye_theta_d(&self, theta: f32) -> f32 {
+        match *self {
+            Self::Fisheye { k1, k2, k3, k4 } => {
+                let t2 = theta * theta;
+                theta * (1.0 + t2 * (k1 + t2 * (k2 + t2 * (k3 + t2 * k4))))
+            }
+            _ => theta,
+        }
+    }
 }
 
 impl HasGlamPosition for CameraProperties {
@@ -63,6 +240,7 @@ impl HasGlamPosition for CameraProperties {
 }
 
 impl HasGlamQuat for CameraProperties {
+    /// Camera to world rotation, see [`crate::math::frames::Camera`].
     fn quat(&self) -> Quat {
         *self
             .quat
@@ -87,6 +265,8 @@ mod tests {
             roll: -0.69,
             img_height: 720,
             img_width: 1280,
+            intrinsics: None,
+            distortion: None,
             quat: OnceLock::new(),
             dir_vec: OnceLock::new(),
             pos: OnceLock::new(),
@@ -97,4 +277,40 @@ mod tests {
             *camera.direction_vector()
         )
     }
+
+    #[test]
+    fn test_undistort_roundtrip() {
+        let models = [
+            Distortion::BrownConrady {
+                k1: -0.28,
+                k2: 0.07,
+                p1: 0.001,
+                p2: -0.0005,
+                k3: 0.0,
+            },
+            Distortion::Fisheye {
+                k1: -0.01,
+                k2: 0.02,
+                k3: -0.005,
+                k4: 0.0,
+            },
+        ];
+
+        for model in models {
+            let p = Vec2::new(0.4, -0.25);
+            let roundtrip = model.undistort(model.distort(p));
+
+            assert!(p.abs_diff_eq(roundtrip, 1e-4), "{model:?}: {roundtrip}");
+        }
+    }
+
+    #[test]
+    fn test_intrinsics_from_fov() {
+        let camera = CameraProperties::test_new();
+        let k = camera.intrinsics_or_fov();
+
+        assert_eq!(k.cx, 640.0);
+        assert_eq!(k.cy, 360.0);
+        assert!((k.fx - 640.0 / (std::f32::consts::FRAC_PI_6).tan()).abs() < 1e-3);
+    }
 }
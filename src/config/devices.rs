@@ -1,60 +1,176 @@
 use std::sync::OnceLock;
 
 use glam::Vec3A;
-use rust_3d::{BoundingBox3D, HasBoundingBox3D, HasBoundingBox3DMaybe, Point3D};
 use serde::Deserialize;
 
+use super::Shape;
 use crate::HasGlamPosition;
 
 #[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "DeviceEntry")]
 pub struct Device {
     pub name: String,
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-    max_x: f32,
-    max_y: f32,
-    max_z: f32,
-    #[serde(skip)]
+    pub shape: Shape,
+    /// Fiducial marker stuck on the device, used to place it automatically.
+    pub tag: Option<u16>,
     pos: OnceLock<Vec3A>,
 }
 
+/// A `[[devices]]` entry as written, either with a `shape` table or the older
+/// `min_*`/`max_*` keys of an axis aligned box.
+#[derive(Deserialize)]
+struct DeviceEntry {
+    name: String,
+    #[serde(default)]
+    tag: Option<u16>,
+    shape: Option<Shape>,
+    min_x: Option<f32>,
+    min_y: Option<f32>,
+    min_z: Option<f32>,
+    max_x: Option<f32>,
+    max_y: Option<f32>,
+    max_z: Option<f32>,
+}
+
+impl TryFrom<DeviceEntry> for Device {
+    type Error = String;
+
+    fn try_from(entry: DeviceEntry) -> Result<Self, Self::Error> {
+        let corners = [
+            entry.min_x,
+            entry.min_y,
+            entry.min_z,
+            entry.max_x,
+            entry.max_y,
+            entry.max_z,
+        ];
+
+        let shape = match (entry.shape, corners) {
+            (Some(shape), [None, None, None, None, None, None]) => shape,
+            (
+                None,
+                [Some(min_x), Some(min_y), Some(min_z), Some(max_x), Some(max_y), Some(max_z)],
+            ) => Shape::Aabb {
+                min: Vec3A::new(min_x, min_y, min_z),
+                max: Vec3A::new(max_x, max_y, max_z),
+            },
+            (Some(_), _) => {
+                return Err(format!(
+                    "device {} has both a shape and min/max corners",
+                    entry.name
+                ))
+            }
+            (None, _) => {
+                return Err(format!(
+                    "device {} needs a shape or all of min_x, min_y, min_z, max_x, max_y and max_z",
+                    entry.name
+                ))
+            }
+        };
+
+        Ok(Self {
+            name: entry.name,
+            shape,
+            tag: entry.tag,
+            pos: OnceLock::new(),
+        })
+    }
+}
+
 impl Device {
+    pub fn new(name: &str, shape: Shape) -> Self {
+        Self {
+            name: name.to_owned(),
+            shape,
+            tag: None,
+            pos: OnceLock::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn test_new(name: &str, min: Vec3A, max: Vec3A) -> Self {
+        Self::new(name, Shape::Aabb { min, max })
+    }
+
+    /// An axis aligned cube with edges `size` long.
+    #[cfg(test)]
+    pub fn test_cube(name: &str, center: Vec3A, size: f32) -> Self {
+        Self::test_new(name, center - size / 2.0, center + size / 2.0)
+    }
+
     pub fn pos_mean(&self) -> &Vec3A {
-        self.pos.get_or_init(|| {
-            Vec3A::new(
-                (self.min_x + self.max_x) / 2.0,
-                (self.min_y + self.max_y) / 2.0,
-                (self.min_z + self.max_z) / 2.0,
-            )
-        })
+        self.pos.get_or_init(|| self.shape.center())
+    }
+}
+
+impl Device {
+    /// Lower corner of the axis aligned box around the device.
+    pub fn min(&self) -> Vec3A {
+        self.shape.bounds().0
+    }
+
+    /// Upper corner of the axis aligned box around the device.
+    pub fn max(&self) -> Vec3A {
+        self.shape.bounds().1
+    }
+
+    /// The shape moved so it is centred on `center`.
+    pub fn recentered(&self, center: Vec3A) -> Shape {
+        self.shape.translated(center - *self.pos_mean())
     }
 }
 
 impl HasGlamPosition for Device {
     fn pos(&self) -> &Vec3A {
-        self.pos.get_or_init(|| {
-            Vec3A::new(
-                (self.min_x + self.max_x) / 2.0,
-                (self.min_y + self.max_y) / 2.0,
-                (self.min_z + self.max_z) / 2.0,
-            )
-        })
+        self.pos_mean()
     }
 }
 
-impl HasBoundingBox3DMaybe for Device {
-    fn bounding_box_maybe(&self) -> rust_3d::Result<BoundingBox3D> {
-        Ok(self.bounding_box())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Devices {
+        devices: Vec<Device>,
     }
-}
 
-impl HasBoundingBox3D for Device {
-    fn bounding_box(&self) -> BoundingBox3D {
-        BoundingBox3D::new(
-            &Point3D::new(self.min_x.into(), self.min_y.into(), self.min_z.into()),
-            &Point3D::new(self.max_x.into(), self.max_y.into(), self.max_z.into()),
+    #[test]
+    fn reads_old_and_new_entries() {
+        let Devices { devices } = toml::from_str(
+            r#"
+            [[devices]]
+            name = "lamp"
+            min_x = 0
+            min_y = 0
+            min_z = 0
+            max_x = 1
+            max_y = 1
+            max_z = 2
+
+            [[devices]]
+            name = "tv"
+            tag = 4
+            shape = { type = "oriented_box", center = [3, 0, 1], size = [0.1, 1.2, 0.7], yaw = 0.3 }
+            "#,
         )
-        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            devices[0].shape,
+            Shape::Aabb {
+                min: Vec3A::ZERO,
+                max: Vec3A::new(1.0, 1.0, 2.0)
+            }
+        );
+        assert_eq!(*devices[1].pos_mean(), Vec3A::new(3.0, 0.0, 1.0));
+        assert_eq!(devices[1].tag, Some(4));
+
+        let missing = toml::from_str::<Devices>("[[devices]]\nname = \"x\"\nmin_x = 1\n");
+        assert!(missing.is_err());
+        let both = toml::from_str::<Devices>(
+            "[[devices]]\nname = \"x\"\nmin_x = 1\nshape = { type = \"sphere\", center = [0, 0, 0], radius = 1 }\n",
+        );
+        assert!(both.is_err());
     }
 }
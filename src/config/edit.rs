@@ -0,0 +1,246 @@
+use std::{fs, path::PathBuf};
+
+use error_stack::{Result, ResultExt};
+use glam::Vec3A;
+use toml_edit::{value, DocumentMut, Item, Table};
+
+use serde::Serialize;
+
+use super::{Distortion, Intrinsics, Shape};
+use crate::GError;
+
+/// Edits the config file in place, keeping its formatting and comments.
+pub struct ConfigEditor {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigEditor {
+    pub fn open(path: PathBuf) -> Result<Self, GError> {
+        let doc = fs::read_to_string(&path)
+            .change_context(GError::ConfigError)
+            .attach_printable("Couldn't read the config file")?
+            .parse::<DocumentMut>()
+            .change_context(GError::ConfigError)?;
+
+        Ok(Self { path, doc })
+    }
+
+    pub fn save(&self) -> Result<(), GError> {
+        fs::write(&self.path, self.doc.to_string())
+            .change_context(GError::ConfigError)
+            .attach_printable("Couldn't write the config file")
+    }
+
+    fn camera_mut(&mut self, camera: &str) -> Result<&mut Table, GError> {
+        self.doc
+            .get_mut(camera)
+            .and_then(Item::as_table_mut)
+            .ok_or(GError::ConfigError)
+            .attach_printable_lazy(|| format!("No [{camera}] table in the config"))
+    }
+
+    /// Writes intrinsics and distortion into the `[camera]` table. The field of
+    /// view is updated to match, so the fallback model stays consistent.
+    pub fn set_intrinsics(
+        &mut self,
+        camera: &str,
+        intrinsics: &Intrinsics,
+        distortion: &Distortion,
+    ) -> Result<(), GError> {
+        let table = self.camera_mut(camera)?;
+
+        let width = table.get("img_width").and_then(Item::as_integer);
+        let height = table.get("img_height").and_then(Item::as_integer);
+        if let (Some(w), Some(h)) = (width, height) {
+            let fov_x = 2.0 * (w as f32 / 2.0 / intrinsics.fx).atan();
+            let fov_y = 2.0 * (h as f32 / 2.0 / intrinsics.fy).atan();
+            table["fov_x"] = float(fov_x);
+            table["fov_y"] = float(fov_y);
+        }
+
+        let mut k = Table::new();
+        k["fx"] = float(intrinsics.fx);
+        k["fy"] = float(intrinsics.fy);
+        k["cx"] = float(intrinsics.cx);
+        k["cy"] = float(intrinsics.cy);
+        table["intrinsics"] = Item::Table(k);
+
+        let mut d = Table::new();
+        match *distortion {
+            Distortion::BrownConrady { k1, k2, p1, p2, k3 } => {
+                d["model"] = value("brown_conrady");
+                d["k1"] = float(k1);
+                d["k2"] = float(k2);
+                d["p1"] = float(p1);
+                d["p2"] = float(p2);
+                d["k3"] = float(k3);
+            }
+            Distortion::Fisheye { k1, k2, k3, k4 } => {
+                d["model"] = value("fisheye");
+                d["k1"] = float(k1);
+                d["k2"] = float(k2);
+                d["k3"] = float(k3);
+                d["k4"] = float(k4);
+            }
+        }
+        table["distortion"] = Item::Table(d);
+
+        Ok(())
+    }
+
+    /// Writes the position and orientation into the `[camera]` table.
+    pub fn set_pose(
+        &mut self,
+        camera: &str,
+        pos: Vec3A,
+        yaw: f32,
+        pitch: f32,
+        roll: f32,
+    ) -> Result<(), GError> {
+        let table = self.camera_mut(camera)?;
+
+        table["pos_x"] = float(pos.x);
+        table["pos_y"] = float(pos.y);
+        table["pos_z"] = float(pos.z);
+        table["pitch"] = float(pitch);
+        table["yaw"] = float(yaw);
+        table["roll"] = float(roll);
+
+        Ok(())
+    }
+
+    /// Replaces the geometry of the `[[devices]]` entry called `name`. Boxes
+    /// of entries still using `min_*`/`max_*` keys are written back that way.
+    pub fn set_device_shape(&mut self, name: &str, shape: &Shape) -> Result<(), GError> {
+        let device = self
+            .doc
+            .get_mut("devices")
+            .and_then(Item::as_array_of_tables_mut)
+            .and_then(|devices| {
+                devices
+                    .iter_mut()
+                    .find(|d| d.get("name").and_then(Item::as_str) == Some(name))
+            })
+            .ok_or(GError::ConfigError)
+            .attach_printable_lazy(|| format!("No device called {name} in the config"))?;
+
+        if let (Shape::Aabb { min, max }, true) = (shape, device.contains_key("min_x")) {
+            device["min_x"] = float(min.x);
+            device["min_y"] = float(min.y);
+            device["min_z"] = float(min.z);
+            device["max_x"] = float(max.x);
+            device["max_y"] = float(max.y);
+            device["max_z"] = float(max.z);
+            return Ok(());
+        }
+
+        for key in ["min_x", "min_y", "min_z", "max_x", "max_y", "max_z"] {
+            device.remove(key);
+        }
+
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            shape: &'a Shape,
+        }
+        let mut entry = toml_edit::ser::to_document(&Entry { shape })
+            .change_context(GError::ConfigError)
+            .attach_printable("Couldn't write the device shape")?;
+        device["shape"] = entry.remove("shape").unwrap_or_default();
+
+        Ok(())
+    }
+}
+
+/// `f32` as a toml float without the noise of widening it to `f64`.
+fn float(v: f32) -> Item {
+    value(v.to_string().parse::<f64>().unwrap_or(v as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_intrinsics() {
+        let mut editor = ConfigEditor {
+            path: PathBuf::new(),
+            doc: "# cameras\n[camera1]\nfov_x = 0.3\nfov_y = 0.3\nimg_height = 100\nimg_width = 200\n"
+                .parse()
+                .unwrap(),
+        };
+
+        editor
+            .set_intrinsics(
+                "camera1",
+                &Intrinsics {
+                    fx: 100.0,
+                    fy: 50.0,
+                    cx: 100.0,
+                    cy: 50.0,
+                },
+                &Distortion::Fisheye {
+                    k1: 0.1,
+                    k2: 0.0,
+                    k3: 0.0,
+                    k4: 0.0,
+                },
+            )
+            .unwrap();
+
+        let out = editor.doc.to_string();
+        assert!(out.starts_with("# cameras\n"));
+        assert!(out.contains("[camera1.intrinsics]"));
+        assert!(out.contains("k1 = 0.1\n"));
+        assert!(out.contains(&format!("fov_x = {}", std::f32::consts::FRAC_PI_2)));
+        assert!(editor
+            .set_intrinsics(
+                "camera3",
+                &Intrinsics {
+                    fx: 1.0,
+                    fy: 1.0,
+                    cx: 0.0,
+                    cy: 0.0,
+                },
+                &Distortion::Fisheye {
+                    k1: 0.0,
+                    k2: 0.0,
+                    k3: 0.0,
+                    k4: 0.0,
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn writes_device_shapes() {
+        let mut editor = ConfigEditor {
+            path: PathBuf::new(),
+            doc: "[[devices]]\nname = \"lamp\"\nmin_x = 0\nmin_y = 0\nmin_z = 0\nmax_x = 1\nmax_y = 1\nmax_z = 1\n\n[[devices]]\nname = \"tv\"\n"
+                .parse()
+                .unwrap(),
+        };
+
+        let lamp = Shape::Aabb {
+            min: Vec3A::ONE,
+            max: Vec3A::splat(2.0),
+        };
+        editor.set_device_shape("lamp", &lamp).unwrap();
+        let tv = Shape::Sphere {
+            center: Vec3A::new(1.0, 2.0, 3.0),
+            radius: 0.5,
+        };
+        editor.set_device_shape("tv", &tv).unwrap();
+        assert!(editor.set_device_shape("fridge", &tv).is_err());
+
+        #[derive(serde::Deserialize)]
+        struct Devices {
+            devices: Vec<super::super::Device>,
+        }
+        let out = editor.doc.to_string();
+        assert!(out.contains("max_x = 2"));
+        let Devices { devices } = toml::from_str(&out).unwrap();
+        assert_eq!(devices[0].shape, lamp);
+        assert_eq!(devices[1].shape, tv);
+    }
+}
@@ -0,0 +1,17 @@
+use glam::Vec3A;
+use serde::Deserialize;
+
+/// A fiducial marker mounted at a known position, used to locate the cameras.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TagAnchor {
+    pub id: u16,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl TagAnchor {
+    pub fn pos(&self) -> Vec3A {
+        Vec3A::new(self.x, self.y, self.z)
+    }
+}
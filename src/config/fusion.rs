@@ -0,0 +1,25 @@
+use serde::Deserialize;
+
+/// How much to trust each cue when the gaze and pointing are fused.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FusionConfig {
+    /// Standard deviation of the gaze direction, in radians.
+    pub gaze_uncertainty: f32,
+    /// Standard deviation of a triangulated keypoint, in world units. The
+    /// pointing direction is less certain the closer its two keypoints are.
+    pub keypoint_error: f32,
+    /// Devices further than this many standard deviations from every cue
+    /// aren't candidates.
+    pub gate: f32,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            gaze_uncertainty: 8f32.to_radians(),
+            keypoint_error: 0.03,
+            gate: 3.0,
+        }
+    }
+}
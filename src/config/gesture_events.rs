@@ -0,0 +1,43 @@
+use serde::Deserialize;
+
+use crate::models::GestureKind;
+
+/// Turning the gesture of each frame into discrete gesture events.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GestureEventsConfig {
+    /// Frames in a row a gesture has to be seen to start, and missed to be
+    /// released, so a single misclassified frame neither starts nor ends one.
+    pub min_frames: usize,
+    /// Seconds between releasing a gesture and starting it again for the two
+    /// to count as a double gesture.
+    pub double_timeout: f32,
+    /// Seconds between the starts of consecutive gestures of a sequence.
+    pub sequence_timeout: f32,
+    pub sequences: Vec<GestureSequence>,
+}
+
+/// Gestures that fire an event of their own when started one after another,
+/// e.g.
+///
+/// ```toml
+/// [[gesture_events.sequences]]
+/// name = "next"
+/// gestures = ["fist", { swipe = "right" }]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct GestureSequence {
+    pub name: String,
+    pub gestures: Vec<GestureKind>,
+}
+
+impl Default for GestureEventsConfig {
+    fn default() -> Self {
+        Self {
+            min_frames: 3,
+            double_timeout: 0.5,
+            sequence_timeout: 1.0,
+            sequences: vec![],
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Hand poses recognised in Rust, on top of the gestures of the gesture
+/// model. Train a model with `gesture-ease train-hands`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HandPoseConfig {
+    /// Trained classifier, the classifier is off without one.
+    pub model: Option<PathBuf>,
+    /// Least fraction of the nearest examples that have to agree on a pose.
+    pub min_agreement: f32,
+    /// Furthest the nearest example of a pose can be for the pose to count,
+    /// in palm sizes, so hands unlike any example aren't forced into a pose.
+    pub max_distance: f32,
+}
+
+impl Default for HandPoseConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            min_agreement: 0.6,
+            max_distance: 1.0,
+        }
+    }
+}
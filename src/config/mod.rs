@@ -1,14 +1,47 @@
-use std::{fs, path::PathBuf, sync::OnceLock};
+use std::{fs, path::PathBuf};
 
 use error_stack::{Report, ResultExt};
-use rust_3d::AABBTree3D;
 use serde::Deserialize;
 
 mod camera;
 mod devices;
-
-pub use camera::CameraProperties;
+mod edit;
+mod fiducials;
+mod fusion;
+mod gesture_events;
+mod hand_poses;
+mod models;
+mod occluders;
+mod pointing;
+mod room;
+mod selection;
+mod shapes;
+mod smoothing;
+mod targeting;
+mod tracking;
+mod triangulation;
+mod uncertainty;
+
+pub use camera::{CameraProperties, Distortion, Intrinsics};
 pub use devices::Device;
+pub use edit::ConfigEditor;
+pub use fiducials::TagAnchor;
+pub use fusion::FusionConfig;
+pub use gesture_events::{GestureEventsConfig, GestureSequence};
+pub use hand_poses::HandPoseConfig;
+pub use models::{
+    Angle, AngleAxes, AngleConvention, AnglePreset, AngleUnit, HpeModel, ModelsConfig, SignedAxis,
+};
+pub use occluders::Occluder;
+pub use pointing::{PointingConfig, PointingRay, TargetCue};
+pub use room::{OutOfRoom, OutOfRoomPolicy, RoomBounds, RoomConfig};
+pub use selection::{SelectionConfig, SelectionMode};
+pub use shapes::Shape;
+pub use smoothing::{OneEuroParams, SmoothingConfig};
+pub use targeting::{TargetingConfig, TargetingMode};
+pub use tracking::TrackingConfig;
+pub use triangulation::TriangulationLimits;
+pub use uncertainty::UncertaintyConfig;
 
 use crate::GError;
 
@@ -17,11 +50,60 @@ pub struct Config {
     pub camera1: CameraProperties,
     pub camera2: CameraProperties,
     pub devices: Vec<Device>,
-    #[serde(skip)]
-    aabbtree: OnceLock<AABBTree3D<Device>>,
+    #[serde(default)]
+    pub occluders: Vec<Occluder>,
+    #[serde(default)]
+    pub tags: Vec<TagAnchor>,
+    #[serde(default)]
+    pub room: RoomConfig,
+    #[serde(default)]
+    pub triangulation: TriangulationLimits,
+    #[serde(default)]
+    pub tracking: TrackingConfig,
+    #[serde(default)]
+    pub smoothing: SmoothingConfig,
+    #[serde(default)]
+    pub targeting: TargetingConfig,
+    #[serde(default)]
+    pub pointing: PointingConfig,
+    #[serde(default)]
+    pub fusion: FusionConfig,
+    #[serde(default)]
+    pub uncertainty: UncertaintyConfig,
+    #[serde(default)]
+    pub selection: SelectionConfig,
+    #[serde(default)]
+    pub gesture_events: GestureEventsConfig,
+    #[serde(default)]
+    pub hand_poses: HandPoseConfig,
+    #[serde(default)]
+    pub models: ModelsConfig,
 }
 
 impl Config {
+    #[cfg(test)]
+    pub fn test_new(devices: Vec<Device>) -> Self {
+        Self {
+            camera1: CameraProperties::test_new(),
+            camera2: CameraProperties::test_new(),
+            devices,
+            occluders: vec![],
+            tags: vec![],
+            room: Default::default(),
+            triangulation: Default::default(),
+            tracking: Default::default(),
+            smoothing: Default::default(),
+            targeting: Default::default(),
+            pointing: Default::default(),
+            fusion: Default::default(),
+            uncertainty: Default::default(),
+            selection: Default::default(),
+            gesture_events: Default::default(),
+            hand_poses: Default::default(),
+            models: Default::default(),
+        }
+    }
+
     pub fn open(path: PathBuf) -> error_stack::Result<Self, GError> {
         toml::from_str(
             &fs::read_to_string(path)
@@ -30,11 +112,6 @@ impl Config {
         )
         .change_context(GError::ConfigError)
     }
-
-    pub fn aabbtree(&self) -> &AABBTree3D<Device> {
-        self.aabbtree
-            .get_or_init(|| AABBTree3D::new(self.devices.clone(), usize::MAX, 1))
-    }
 }
 
 impl TryFrom<PathBuf> for Config {
@@ -52,7 +129,7 @@ impl TryFrom<PathBuf> for Config {
 
 #[cfg(test)]
 mod tests {
-    use super::Config;
+    use super::{Config, TriangulationLimits};
 
     #[test]
     fn parse_config() {
@@ -66,6 +143,19 @@ mod tests {
         pitch = -1
         yaw = -0.5
         roll = 0
+        img_height = 972
+        img_width = 1296
+
+        [camera1.intrinsics]
+        fx = 1100.0
+        fy = 1100.0
+        cx = 648.0
+        cy = 486.0
+
+        [camera1.distortion]
+        model = "brown_conrady"
+        k1 = -0.3
+        k2 = 0.1
 
         [camera2]
         fov_x = 0.3
@@ -76,6 +166,8 @@ mod tests {
         pitch = -1
         yaw = 1
         roll = 0
+        img_height = 972
+        img_width = 1296
 
         [[devices]]
         name = "Fist of Family Values"
@@ -93,10 +185,43 @@ mod tests {
         min_z = 0
         max_x = 37
         max_y = 37
-        max_z = -37"#;
+        max_z = -37
+        tag = 3
+
+        [[tags]]
+        id = 0
+        x = 1
+        y = 0
+        z = 2
+
+        [[occluders]]
+        name = "pillar"
+        shape = { type = "oriented_box", center = [2, 2, 1], size = [0.3, 0.3, 2.5], yaw = 0.2 }
+
+        [triangulation]
+        max_ray_gap = 5
+
+        [smoothing.orientation]
+        beta = 0.1"#;
 
         let config: Config = toml::from_str(config_toml).unwrap();
 
         assert_eq!(config.devices.len(), 2);
+        assert_eq!(config.devices[1].tag, Some(3));
+        assert_eq!(config.tags.len(), 1);
+        assert_eq!(config.occluders[0].name.as_deref(), Some("pillar"));
+        assert!(config.camera1.intrinsics.is_some());
+        assert!(matches!(
+            config.camera1.distortion,
+            Some(crate::config::Distortion::BrownConrady { k3, .. }) if k3 == 0.0
+        ));
+        assert!(config.camera2.intrinsics.is_none());
+        assert_eq!(config.triangulation.max_ray_gap, 5.0);
+        assert_eq!(
+            config.triangulation.max_reprojection_error,
+            TriangulationLimits::default().max_reprojection_error
+        );
+        assert_eq!(config.smoothing.orientation.beta, 0.1);
+        assert_eq!(config.smoothing.orientation.min_cutoff, 1.0);
     }
 }
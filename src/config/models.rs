@@ -0,0 +1,298 @@
+use glam::{Quat, Vec3A};
+use serde::Deserialize;
+
+use super::CameraProperties;
+
+/// Settings for the model processes.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ModelsConfig {
+    pub hpe: HpeModel,
+    /// Furthest apart detections of different models on the same frame can
+    /// be to be paired, like a gesture and a head pose box, as a fraction of
+    /// the image width.
+    pub max_pair_offset: f32,
+}
+
+impl ModelsConfig {
+    /// [`Self::max_pair_offset`] in pixels of `camera`'s images.
+    pub fn max_pair_pixels(&self, camera: &CameraProperties) -> f32 {
+        self.max_pair_offset * camera.img_width as f32
+    }
+}
+
+impl Default for ModelsConfig {
+    fn default() -> Self {
+        Self {
+            hpe: HpeModel::default(),
+            max_pair_offset: 0.06,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HpeModel {
+    /// How to read the yaw, pitch and roll the model reports.
+    pub angles: AngleConvention,
+    /// Estimate head poses in every camera's frame and fuse them, instead
+    /// of only in camera 1's. Costs a model run per camera.
+    pub all_cameras: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Angle {
+    Yaw,
+    Pitch,
+    Roll,
+}
+
+/// A head frame axis, written `"x"`, `"-y"` and so on.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub struct SignedAxis(Vec3A);
+
+impl TryFrom<String> for SignedAxis {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        let (sign, axis) = match s.strip_prefix('-') {
+            Some(axis) => (-1.0, axis),
+            None => (1.0, s.strip_prefix('+').unwrap_or(&s)),
+        };
+        let axis = match axis {
+            "x" => Vec3A::X,
+            "y" => Vec3A::Y,
+            "z" => Vec3A::Z,
+            _ => {
+                return Err(format!(
+                    "`{s}` isn't an axis, expected x, y or z with an optional sign"
+                ))
+            }
+        };
+        Ok(Self(sign * axis))
+    }
+}
+
+/// The head frame axis each angle turns about.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AngleAxes {
+    pub yaw: SignedAxis,
+    pub pitch: SignedAxis,
+    pub roll: SignedAxis,
+}
+
+/// Conventions of models we've used, to start from.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnglePreset {
+    /// Radians, yaw about z, then pitch about y, then roll about x, the same
+    /// as the cameras.
+    #[default]
+    Radians,
+    /// Like `radians` but in degrees.
+    Degrees,
+    /// DirectMHP and the Hopenet family: degrees, with pitch applied last
+    /// about the image's horizontal axis and roll first about the view axis.
+    Directmhp,
+}
+
+/// How a model's yaw, pitch and roll make up a rotation. The rotation is
+/// the product of turning about the axis of each angle in `order`, so the
+/// first angle is applied last, like [`glam::EulerRot`].
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "AngleConventionEntry")]
+pub struct AngleConvention {
+    pub unit: AngleUnit,
+    pub order: [Angle; 3],
+    pub axes: AngleAxes,
+}
+
+impl AngleConvention {
+    pub fn preset(preset: AnglePreset) -> Self {
+        let zyx = AngleAxes {
+            yaw: SignedAxis(Vec3A::Z),
+            pitch: SignedAxis(Vec3A::Y),
+            roll: SignedAxis(Vec3A::X),
+        };
+
+        match preset {
+            AnglePreset::Radians => Self {
+                unit: AngleUnit::Radians,
+                order: [Angle::Yaw, Angle::Pitch, Angle::Roll],
+                axes: zyx,
+            },
+            AnglePreset::Degrees => Self {
+                unit: AngleUnit::Degrees,
+                ..Self::preset(AnglePreset::Radians)
+            },
+            // turns about the image's down and right axes, which for a face
+            // looking at the camera are our -z and -y
+            AnglePreset::Directmhp => Self {
+                unit: AngleUnit::Degrees,
+                order: [Angle::Pitch, Angle::Yaw, Angle::Roll],
+                axes: AngleAxes {
+                    yaw: SignedAxis(-Vec3A::Z),
+                    pitch: SignedAxis(-Vec3A::Y),
+                    roll: SignedAxis(Vec3A::X),
+                },
+            },
+        }
+    }
+
+    /// Rotation for the angles as reported by the model.
+    pub fn quat(&self, yaw: f32, pitch: f32, roll: f32) -> Quat {
+        let scale = match self.unit {
+            AngleUnit::Radians => 1.0,
+            AngleUnit::Degrees => 1f32.to_radians(),
+        };
+
+        self.order
+            .iter()
+            .map(|angle| {
+                let (axis, value) = match angle {
+                    Angle::Yaw => (self.axes.yaw, yaw),
+                    Angle::Pitch => (self.axes.pitch, pitch),
+                    Angle::Roll => (self.axes.roll, roll),
+                };
+                Quat::from_axis_angle(axis.0.into(), value * scale)
+            })
+            .fold(Quat::IDENTITY, |q, r| q * r)
+    }
+}
+
+impl Default for AngleConvention {
+    fn default() -> Self {
+        Self::preset(AnglePreset::default())
+    }
+}
+
+/// A preset with any of its fields overridden.
+#[derive(Deserialize)]
+struct AngleConventionEntry {
+    #[serde(default)]
+    preset: AnglePreset,
+    unit: Option<AngleUnit>,
+    order: Option<[Angle; 3]>,
+    axes: Option<AngleAxes>,
+}
+
+impl TryFrom<AngleConventionEntry> for AngleConvention {
+    type Error = String;
+
+    fn try_from(entry: AngleConventionEntry) -> Result<Self, String> {
+        let preset = Self::preset(entry.preset);
+        let order = entry.order.unwrap_or(preset.order);
+
+        if order[0] == order[1] || order[1] == order[2] || order[0] == order[2] {
+            return Err(format!(
+                "`order` has to list each angle once, got {order:?}"
+            ));
+        }
+
+        Ok(Self {
+            unit: entry.unit.unwrap_or(preset.unit),
+            order,
+            axes: entry.axes.unwrap_or(preset.axes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::EulerRot;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Models {
+        models: ModelsConfig,
+    }
+
+    fn parse(s: &str) -> AngleConvention {
+        toml::from_str::<Models>(s).unwrap().models.hpe.angles
+    }
+
+    #[test]
+    fn presets() {
+        let (yaw, pitch, roll) = (0.3, -0.2, 0.1);
+        let zyx = Quat::from_euler(EulerRot::ZYX, yaw, pitch, roll);
+
+        let radians = AngleConvention::default();
+        assert!(radians.quat(yaw, pitch, roll).abs_diff_eq(zyx, 1e-6));
+
+        let degrees = parse("[models.hpe.angles]\npreset = \"degrees\"");
+        let q = degrees.quat(yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees());
+        assert!(q.abs_diff_eq(zyx, 1e-6));
+
+        // pitch turns about -y after yaw about -z and roll about x
+        let mhp = parse("[models.hpe.angles]\npreset = \"directmhp\"");
+        let q = mhp.quat(30.0, 20.0, 10.0);
+        let expected = Quat::from_euler(
+            EulerRot::YZX,
+            -20f32.to_radians(),
+            -30f32.to_radians(),
+            10f32.to_radians(),
+        );
+        assert!(q.abs_diff_eq(expected, 1e-6));
+
+        // a pure yaw turns the face the other way from the default
+        let turned = mhp.quat(90.0, 0.0, 0.0) * Vec3A::X;
+        assert!(turned.abs_diff_eq(-Vec3A::Y, 1e-6));
+    }
+
+    #[test]
+    fn overrides_and_errors() {
+        let custom = parse(
+            r#"
+            [models.hpe.angles]
+            preset = "degrees"
+            order = ["roll", "pitch", "yaw"]
+            axes = { yaw = "-z", pitch = "+y", roll = "x" }
+            "#,
+        );
+        assert_eq!(custom.unit, AngleUnit::Degrees);
+        let q = custom.quat(10.0, 20.0, 30.0);
+        let expected = Quat::from_euler(
+            EulerRot::XYZ,
+            30f32.to_radians(),
+            20f32.to_radians(),
+            -10f32.to_radians(),
+        );
+        assert!(q.abs_diff_eq(expected, 1e-6));
+
+        let unit_only = parse("[models.hpe.angles]\nunit = \"degrees\"");
+        assert_eq!(unit_only, AngleConvention::preset(AnglePreset::Degrees));
+
+        assert!(toml::from_str::<Models>(
+            "[models.hpe.angles]\naxes = { yaw = \"w\", pitch = \"y\", roll = \"x\" }"
+        )
+        .is_err());
+        assert!(toml::from_str::<Models>(
+            "[models.hpe.angles]\norder = [\"yaw\", \"yaw\", \"roll\"]"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn pair_offset_scales_with_the_image() {
+        let models = toml::from_str::<Models>("[models]\nmax_pair_offset = 0.1")
+            .unwrap()
+            .models;
+        let mut camera = CameraProperties::test_new();
+        camera.img_width = 640;
+        assert_eq!(models.max_pair_pixels(&camera), 64.0);
+
+        camera.img_width = 1296;
+        assert!((models.max_pair_pixels(&camera) - 129.6).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+use super::Shape;
+
+/// Something in the room that blocks the view of devices behind it, like a
+/// wall, shelf or pillar.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Occluder {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub shape: Shape,
+}
@@ -0,0 +1,47 @@
+use serde::Deserialize;
+
+/// Aiming by pointing, from the arm keypoints of the gesture model.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PointingConfig {
+    pub cue: TargetCue,
+    pub ray: PointingRay,
+    /// Keypoints less confident than this in either camera are ignored.
+    pub min_confidence: f32,
+}
+
+/// Which line picks the target.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetCue {
+    /// Where the head is facing.
+    #[default]
+    Gaze,
+    /// Where the arm is pointing, nothing if it isn't seen.
+    Pointing,
+    /// Where the arm is pointing, or the gaze when the arm isn't seen.
+    PointingOrGaze,
+    /// Both, weighted by how certain each is, see `[fusion]`.
+    Fused,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PointingRay {
+    /// From the eyes through the fingertip, or the wrist, which is where
+    /// people actually aim.
+    #[default]
+    EyeToFingertip,
+    /// Along the arm, from the shoulder or elbow out through the hand.
+    Arm,
+}
+
+impl Default for PointingConfig {
+    fn default() -> Self {
+        Self {
+            cue: TargetCue::default(),
+            ray: PointingRay::default(),
+            min_confidence: 0.5,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use glam::Vec3A;
+use serde::Deserialize;
+
+/// Where people can be, to throw out triangulations that land somewhere
+/// impossible. Every check is off until configured.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RoomConfig {
+    /// Height of the floor.
+    pub floor: Option<f32>,
+    /// Corners of the box the room fits in.
+    pub bounds: Option<RoomBounds>,
+    /// Lowest and highest a head can be above the floor, from sitting on the
+    /// floor to the tallest person in the house.
+    pub head_height: Option<[f32; 2]>,
+    pub out_of_room: OutOfRoomPolicy,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RoomBounds {
+    pub min: Vec3A,
+    pub max: Vec3A,
+}
+
+/// What to do with a head outside the room.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfRoomPolicy {
+    /// Drop the triangulation.
+    #[default]
+    Reject,
+    /// Move the head to the nearest place it could be.
+    Clamp,
+}
+
+/// Why a head can't be where it was triangulated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutOfRoom {
+    /// This far under the floor.
+    BelowFloor(f32),
+    /// This far outside the bounds.
+    OutsideBounds(f32),
+    /// At this height above the floor.
+    HeadHeight(f32),
+}
+
+impl fmt::Display for OutOfRoom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BelowFloor(depth) => write!(f, "{depth:.2} below the floor"),
+            Self::OutsideBounds(distance) => write!(f, "{distance:.2} outside the room"),
+            Self::HeadHeight(height) => write!(f, "head {height:.2} above the floor"),
+        }
+    }
+}
+
+impl RoomConfig {
+    fn floor_or_zero(&self) -> f32 {
+        self.floor.unwrap_or(0.0)
+    }
+
+    /// Why a head can't be at `p`, `None` if it can.
+    pub fn check_head(&self, p: Vec3A) -> Option<OutOfRoom> {
+        if let Some(floor) = self.floor {
+            if p.z < floor {
+                return Some(OutOfRoom::BelowFloor(floor - p.z));
+            }
+        }
+
+        if let Some(RoomBounds { min, max }) = self.bounds {
+            let distance = p.distance(p.clamp(min.min(max), min.max(max)));
+            if distance > 0.0 {
+                return Some(OutOfRoom::OutsideBounds(distance));
+            }
+        }
+
+        let height = p.z - self.floor_or_zero();
+        match self.head_height {
+            Some([low, high]) if height < low || height > high => {
+                Some(OutOfRoom::HeadHeight(height))
+            }
+            _ => None,
+        }
+    }
+
+    /// The nearest place to `p` a head can be.
+    pub fn clamp_head(&self, p: Vec3A) -> Vec3A {
+        let mut p = p;
+
+        if let Some(RoomBounds { min, max }) = self.bounds {
+            p = p.clamp(min.min(max), min.max(max));
+        }
+        if let Some([low, high]) = self.head_height {
+            let floor = self.floor_or_zero();
+            p.z = p.z.clamp(floor + low, floor + high);
+        }
+        if let Some(floor) = self.floor {
+            p.z = p.z.max(floor);
+        }
+
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_and_clamps_heads() {
+        let room: RoomConfig = toml::from_str(
+            r#"
+            floor = -1.0
+            head_height = [0.8, 2.1]
+            out_of_room = "clamp"
+            bounds = { min = [-5, -4, -1], max = [5, 4, 2] }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(room.out_of_room, OutOfRoomPolicy::Clamp);
+
+        let standing = Vec3A::new(1.0, 1.0, 0.7);
+        assert_eq!(room.check_head(standing), None);
+
+        let under = Vec3A::new(1.0, 1.0, -1.5);
+        assert_eq!(room.check_head(under), Some(OutOfRoom::BelowFloor(0.5)));
+        assert!(room
+            .clamp_head(under)
+            .abs_diff_eq(Vec3A::new(1.0, 1.0, -0.2), 1e-6));
+
+        let outside = Vec3A::new(8.0, 1.0, 0.7);
+        assert_eq!(
+            room.check_head(outside),
+            Some(OutOfRoom::OutsideBounds(3.0))
+        );
+        assert_eq!(room.clamp_head(outside), Vec3A::new(5.0, 1.0, 0.7));
+
+        let crouching = Vec3A::new(1.0, 1.0, -0.5);
+        assert!(matches!(
+            room.check_head(crouching),
+            Some(OutOfRoom::HeadHeight(h)) if (h - 0.5).abs() < 1e-6
+        ));
+
+        // nothing configured, nothing rejected
+        assert_eq!(RoomConfig::default().check_head(under * 100.0), None);
+        assert_eq!(RoomConfig::default().clamp_head(under), under);
+    }
+}
@@ -0,0 +1,34 @@
+use serde::Deserialize;
+
+/// How a person picks a device.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SelectionConfig {
+    pub mode: SelectionMode,
+    /// Seconds the gaze has to stay on a device to select it.
+    pub dwell_time: f32,
+    /// Seconds the gaze can leave a device before its focus is lost. Shorter
+    /// glances away neither reset nor advance the dwell.
+    pub leave_time: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    /// A gesture acts on the device being looked at.
+    #[default]
+    Gesture,
+    /// Looking at a device for `dwell_time` selects it, for people who can't
+    /// gesture reliably.
+    Dwell,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        Self {
+            mode: SelectionMode::default(),
+            dwell_time: 1.5,
+            leave_time: 0.3,
+        }
+    }
+}
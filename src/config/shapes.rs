@@ -0,0 +1,236 @@
+use std::f32::consts::TAU;
+
+use glam::{EulerRot, Quat, Vec3A};
+use serde::{Deserialize, Serialize};
+
+/// Geometry of a device. Rotations are in radians, applied yaw, pitch then
+/// roll like the cameras'.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Shape {
+    /// Axis aligned box between two corners.
+    #[serde(rename = "box")]
+    Aabb {
+        min: Vec3A,
+        max: Vec3A,
+    },
+    /// Box of edge lengths `size` turned about its centre.
+    OrientedBox {
+        center: Vec3A,
+        size: Vec3A,
+        #[serde(default)]
+        yaw: f32,
+        #[serde(default)]
+        pitch: f32,
+        #[serde(default)]
+        roll: f32,
+    },
+    Sphere {
+        center: Vec3A,
+        radius: f32,
+    },
+    /// A small target, hit by lines passing within `radius` of it.
+    Point {
+        pos: Vec3A,
+        radius: f32,
+    },
+    /// Union of several shapes, like a speaker pair or an L shaped desk.
+    Compound {
+        shapes: Vec<Shape>,
+    },
+}
+
+impl Shape {
+    /// Corners of the axis aligned box around the shape.
+    pub fn bounds(&self) -> (Vec3A, Vec3A) {
+        match self {
+            Self::Aabb { min, max } => (min.min(*max), min.max(*max)),
+            Self::OrientedBox { center, size, .. } => {
+                let rot = self.quat().unwrap_or_default();
+                let extent = [Vec3A::X, Vec3A::Y, Vec3A::Z]
+                    .into_iter()
+                    .zip(size.to_array())
+                    .map(|(axis, len)| (rot * axis * len / 2.0).abs())
+                    .sum::<Vec3A>();
+                (*center - extent, *center + extent)
+            }
+            Self::Sphere { center, radius } => (*center - *radius, *center + *radius),
+            Self::Point { pos, radius } => (*pos - *radius, *pos + *radius),
+            Self::Compound { shapes } => shapes
+                .iter()
+                .map(Shape::bounds)
+                .reduce(|(min1, max1), (min2, max2)| (min1.min(min2), max1.max(max2)))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn center(&self) -> Vec3A {
+        match self {
+            Self::OrientedBox { center, .. } | Self::Sphere { center, .. } => *center,
+            Self::Point { pos, .. } => *pos,
+            Self::Aabb { .. } | Self::Compound { .. } => {
+                let (min, max) = self.bounds();
+                (min + max) / 2.0
+            }
+        }
+    }
+
+    /// Centre and radius of a sphere enclosing the shape.
+    pub fn bounding_sphere(&self) -> (Vec3A, f32) {
+        match self {
+            Self::Sphere { center, radius } => (*center, *radius),
+            Self::Point { pos, radius } => (*pos, *radius),
+            Self::OrientedBox { center, size, .. } => (*center, size.length() / 2.0),
+            Self::Aabb { .. } | Self::Compound { .. } => {
+                let (min, max) = self.bounds();
+                ((min + max) / 2.0, (max - min).length() / 2.0)
+            }
+        }
+    }
+
+    /// Orientation of an oriented box, mapping its axes into the world.
+    pub fn quat(&self) -> Option<Quat> {
+        match self {
+            Self::OrientedBox {
+                yaw, pitch, roll, ..
+            } => Some(Quat::from_euler(EulerRot::ZYX, *yaw, *pitch, *roll)),
+            _ => None,
+        }
+    }
+
+    /// Edges outlining the shape for drawing: the twelve edges of a box, or
+    /// three great circles for spheres and points.
+    pub fn wireframe(&self) -> Vec<(Vec3A, Vec3A)> {
+        match self {
+            Self::Aabb { min, max } => box_edges(|c| *min + (*max - *min) * c),
+            Self::OrientedBox { center, size, .. } => {
+                let rot = self.quat().unwrap_or_default();
+                box_edges(|c| *center + rot * (*size * (c - 0.5)))
+            }
+            Self::Sphere { center, radius } => circle_edges(*center, *radius),
+            Self::Point { pos, radius } => circle_edges(*pos, *radius),
+            Self::Compound { shapes } => shapes.iter().flat_map(Shape::wireframe).collect(),
+        }
+    }
+
+    /// The same shape moved by `offset`.
+    pub fn translated(&self, offset: Vec3A) -> Self {
+        let mut shape = self.clone();
+        shape.translate(offset);
+        shape
+    }
+
+    fn translate(&mut self, offset: Vec3A) {
+        match self {
+            Self::Aabb { min, max } => {
+                *min += offset;
+                *max += offset;
+            }
+            Self::OrientedBox { center, .. } | Self::Sphere { center, .. } => *center += offset,
+            Self::Point { pos, .. } => *pos += offset,
+            Self::Compound { shapes } => shapes.iter_mut().for_each(|s| s.translate(offset)),
+        }
+    }
+}
+
+/// Edges of a box whose corner at `(x, y, z)`, with each in {0, 1}, is
+/// `corner(x, y, z)`.
+fn box_edges(corner: impl Fn(Vec3A) -> Vec3A) -> Vec<(Vec3A, Vec3A)> {
+    let mut edges = vec![];
+    for i in 0..8u32 {
+        let c = Vec3A::new((i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2 & 1) as f32);
+        // one edge per axis the corner is at the low end of
+        for axis in [Vec3A::X, Vec3A::Y, Vec3A::Z] {
+            if c.dot(axis) == 0.0 {
+                edges.push((corner(c), corner(c + axis)));
+            }
+        }
+    }
+    edges
+}
+
+const CIRCLE_SEGMENTS: usize = 24;
+
+fn circle_edges(center: Vec3A, radius: f32) -> Vec<(Vec3A, Vec3A)> {
+    let mut edges = vec![];
+    for (u, v) in [
+        (Vec3A::X, Vec3A::Y),
+        (Vec3A::Y, Vec3A::Z),
+        (Vec3A::Z, Vec3A::X),
+    ] {
+        let at = |i: usize| {
+            let a = i as f32 * TAU / CIRCLE_SEGMENTS as f32;
+            center + radius * (a.cos() * u + a.sin() * v)
+        };
+        edges.extend((0..CIRCLE_SEGMENTS).map(|i| (at(i), at(i + 1))));
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Shapes {
+        shapes: Vec<Shape>,
+    }
+
+    #[test]
+    fn parses_and_bounds_shapes() {
+        let Shapes { shapes } = toml::from_str(
+            r#"
+            [[shapes]]
+            type = "box"
+            min = [0, 0, 0]
+            max = [1, 2, 3]
+
+            [[shapes]]
+            type = "oriented_box"
+            center = [0, 0, 0]
+            size = [2, 2, 0]
+            yaw = 0.7853982
+
+            [[shapes]]
+            type = "sphere"
+            center = [1, 1, 1]
+            radius = 0.5
+
+            [[shapes]]
+            type = "compound"
+            shapes = [
+                { type = "point", pos = [5, 0, 0], radius = 0.1 },
+                { type = "box", min = [0, 0, 0], max = [1, 1, 1] },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(shapes[0].center(), Vec3A::new(0.5, 1.0, 1.5));
+
+        // a square turned 45 degrees spans its diagonal
+        let (min, max) = shapes[1].bounds();
+        assert!(max.abs_diff_eq(Vec3A::new(2f32.sqrt(), 2f32.sqrt(), 0.0), 1e-5));
+        assert!(min.abs_diff_eq(-max, 1e-6));
+
+        assert_eq!(shapes[2].bounding_sphere(), (Vec3A::ONE, 0.5));
+
+        let (min, max) = shapes[3].bounds();
+        assert_eq!(min, Vec3A::new(0.0, -0.1, -0.1));
+        assert_eq!(max, Vec3A::new(5.1, 1.0, 1.0));
+        assert_eq!(
+            shapes[3].translated(Vec3A::X).bounds(),
+            (min + Vec3A::X, max + Vec3A::X)
+        );
+
+        let edges = shapes[0].wireframe();
+        assert_eq!(edges.len(), 12);
+        for (a, b) in &edges {
+            assert_eq!((*b - *a).cmpne(Vec3A::ZERO).bitmask().count_ones(), 1);
+        }
+        // the turned square's corners lie on the axes
+        assert!(shapes[1].wireframe().iter().all(|(a, _)| {
+            (a.length() - 2f32.sqrt()).abs() < 1e-5 && a.x.abs().min(a.y.abs()) < 1e-5
+        }));
+    }
+}
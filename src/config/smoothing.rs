@@ -0,0 +1,32 @@
+use serde::Deserialize;
+
+/// Filters applied to each tracked person.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SmoothingConfig {
+    pub position: OneEuroParams,
+    pub orientation: OneEuroParams,
+}
+
+/// Tuning of a One Euro filter. Lower `min_cutoff` for less jitter when still,
+/// higher `beta` for less lag when moving.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct OneEuroParams {
+    /// Cutoff frequency at rest, in Hz.
+    pub min_cutoff: f32,
+    /// How much the cutoff rises with speed.
+    pub beta: f32,
+    /// Cutoff frequency of the speed estimate, in Hz.
+    pub d_cutoff: f32,
+}
+
+impl Default for OneEuroParams {
+    fn default() -> Self {
+        Self {
+            min_cutoff: 1.0,
+            beta: 0.5,
+            d_cutoff: 1.0,
+        }
+    }
+}
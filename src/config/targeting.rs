@@ -0,0 +1,37 @@
+use serde::Deserialize;
+
+/// How a line of sight picks the device it's aimed at.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TargetingConfig {
+    pub mode: TargetingMode,
+    /// Largest angle between the line of sight and the edge of a device for
+    /// it to be a candidate, in radians.
+    pub max_cone_angle: f32,
+    /// How quickly the score falls off with distance, per world unit. 0 makes
+    /// distance count only through the apparent size.
+    pub distance_falloff: f32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetingMode {
+    /// The first device the line runs into.
+    #[default]
+    Ray,
+    /// The best scoring device within a cone around the line.
+    Cone,
+    /// The device the line most likely runs into given the measurement
+    /// noise, if it's likely enough, see `[uncertainty]`.
+    Probabilistic,
+}
+
+impl Default for TargetingConfig {
+    fn default() -> Self {
+        Self {
+            mode: TargetingMode::default(),
+            max_cone_angle: 10f32.to_radians(),
+            distance_falloff: 0.1,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+/// How people are followed from frame to frame.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// Standard deviation of the unmodelled acceleration, in world units/s².
+    pub acceleration_noise: f32,
+    /// Standard deviation of a triangulated head position, in world units.
+    pub measurement_noise: f32,
+    /// Largest Mahalanobis distance between a track and an observation that
+    /// can still be the same person.
+    pub gate: f32,
+    /// Frames a new track has to be seen in before it's reported.
+    pub confirm_hits: u32,
+    /// Frames in a row a confirmed track can go unseen before it's dropped.
+    pub max_missed: u32,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            acceleration_noise: 2.0,
+            measurement_noise: 0.05,
+            gate: 3.5,
+            confirm_hits: 3,
+            max_missed: 10,
+        }
+    }
+}
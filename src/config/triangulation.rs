@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// Limits a triangulated point has to stay within to be trusted.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TriangulationLimits {
+    /// Largest distance between the point and any of the rays, in world units.
+    pub max_ray_gap: f32,
+    /// Largest reprojection error in any camera, in pixels.
+    pub max_reprojection_error: f32,
+    /// Smallest angle between any two rays, in radians. Nearly parallel rays
+    /// put the point anywhere along them.
+    pub min_ray_angle: f32,
+}
+
+impl Default for TriangulationLimits {
+    fn default() -> Self {
+        Self {
+            max_ray_gap: 0.2,
+            max_reprojection_error: 25.0,
+            min_ray_angle: 2f32.to_radians(),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+/// Measurement noise for the probabilistic targeting.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UncertaintyConfig {
+    /// Standard deviation of a detection's position in the image, in pixels.
+    pub pixel_noise: f32,
+    /// Standard deviation of the direction of a line of sight about each of
+    /// the two axes across it, in radians.
+    pub angle_noise: f32,
+    /// Lines of sight sampled per estimate. The samples are the same every
+    /// time, so equal inputs give equal probabilities.
+    pub samples: usize,
+    /// Least probability of a device for it to be acted on.
+    pub min_confidence: f32,
+}
+
+impl Default for UncertaintyConfig {
+    fn default() -> Self {
+        Self {
+            pixel_noise: 2.0,
+            angle_noise: 5f32.to_radians(),
+            samples: 256,
+            min_confidence: 0.6,
+        }
+    }
+}
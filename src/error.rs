@@ -10,6 +10,9 @@ pub enum GError {
     ConfigError,
     ModelUninit,
     CameraError,
+    ImageError,
+    CalibrationError,
+    ClassifierError,
 }
 
 impl fmt::Display for GError {
@@ -21,6 +24,9 @@ impl fmt::Display for GError {
             Self::MathError => write!(f, "Error in math operation"),
             Self::ModelUninit => write!(f, "Model used before initializing"),
             Self::CameraError => write!(f, "Camera Error"),
+            Self::ImageError => write!(f, "Error in loading or processing an image"),
+            Self::CalibrationError => write!(f, "Error during camera calibration"),
+            Self::ClassifierError => write!(f, "Error in training or loading a classifier"),
         }
     }
 }
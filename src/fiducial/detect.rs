@@ -0,0 +1,466 @@
+use glam::Vec2;
+use nalgebra::{Matrix3, Vector3};
+
+use super::dictionary::{self, lookup};
+use crate::{calibration::homography, imgproc::GrayImage};
+
+/// Cells along a marker edge, the 4x4 tag16h5 code plus a one cell black
+/// border.
+pub const MARKER_CELLS: usize = 6;
+
+const THRESHOLD_RADIUS: usize = 12;
+const THRESHOLD_OFFSET: f32 = 0.04;
+const MIN_SIDE: f32 = 14.0;
+const MIN_QUAD_FILL: f32 = 0.85;
+const MIN_CELL_CONTRAST: f32 = 0.15;
+const MAX_BORDER_ERRORS: usize = 2;
+const EDGE_SAMPLES: usize = 12;
+const MAX_CORNER_SHIFT: f32 = 3.0;
+
+/// A marker found in an image.
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub id: u16,
+    /// Marker corners in pixels, starting at the top left of the marker and
+    /// going clockwise.
+    pub corners: [Vec2; 4],
+    /// Bits that had to be corrected.
+    pub errors: u32,
+}
+
+impl Detection {
+    /// Projective centre of the marker, where its diagonals cross.
+    pub fn center(&self) -> Vec2 {
+        let [a, b, c, d] = self.corners;
+        let (r, s) = (c - a, d - b);
+        let denom = r.perp_dot(s);
+
+        if denom.abs() < f32::EPSILON {
+            return (a + b + c + d) / 4.0;
+        }
+
+        a + r * ((b - a).perp_dot(s) / denom)
+    }
+}
+
+/// Finds all dictionary markers in `img`.
+pub fn detect_markers(img: &GrayImage) -> Vec<Detection> {
+    let dark = threshold(img);
+    let mut detections: Vec<Detection> = vec![];
+
+    for component in components(&dark, img.width(), img.height()) {
+        let Some(quad) = fit_quad(&component) else {
+            continue;
+        };
+
+        let quad = refine_quad(img, quad);
+        let Some(detection) = decode(img, quad) else {
+            continue;
+        };
+
+        match detections.iter_mut().find(|d| d.id == detection.id) {
+            Some(existing) if existing.errors > detection.errors => *existing = detection,
+            Some(_) => {}
+            None => detections.push(detection),
+        }
+    }
+
+    detections
+}
+
+/// Draws marker `id` with `cell` pixels per cell and a one cell white margin.
+pub fn render_marker(id: u16, cell: usize) -> Option<GrayImage> {
+    let code = *dictionary::dictionary().get(id as usize)?;
+    let size = (MARKER_CELLS + 2) * cell;
+
+    Some(GrayImage::from_fn(size, size, |x, y| {
+        let (cx, cy) = (x / cell, y / cell);
+        let white = if cx == 0 || cy == 0 || cx > MARKER_CELLS || cy > MARKER_CELLS {
+            true
+        } else if cx == 1 || cy == 1 || cx == MARKER_CELLS || cy == MARKER_CELLS {
+            false
+        } else {
+            dictionary::bit(code, cy - 2, cx - 2)
+        };
+
+        if white {
+            1.0
+        } else {
+            0.0
+        }
+    }))
+}
+
+/// Pixels darker than their neighbourhood.
+fn threshold(img: &GrayImage) -> Vec<bool> {
+    let (w, h) = (img.width(), img.height());
+
+    // summed area table with a zero row and column in front
+    let mut sat = vec![0.0f64; (w + 1) * (h + 1)];
+    for y in 0..h {
+        let mut row = 0.0;
+        for x in 0..w {
+            row += img.get(x, y) as f64;
+            sat[(y + 1) * (w + 1) + x + 1] = sat[y * (w + 1) + x + 1] + row;
+        }
+    }
+
+    let mut dark = vec![false; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let (x0, y0) = (
+                x.saturating_sub(THRESHOLD_RADIUS),
+                y.saturating_sub(THRESHOLD_RADIUS),
+            );
+            let (x1, y1) = (
+                (x + THRESHOLD_RADIUS + 1).min(w),
+                (y + THRESHOLD_RADIUS + 1).min(h),
+            );
+            let sum = sat[y1 * (w + 1) + x1] - sat[y0 * (w + 1) + x1] - sat[y1 * (w + 1) + x0]
+                + sat[y0 * (w + 1) + x0];
+            let mean = sum / ((x1 - x0) * (y1 - y0)) as f64;
+
+            dark[y * w + x] = img.get(x, y) < mean as f32 - THRESHOLD_OFFSET;
+        }
+    }
+
+    dark
+}
+
+/// Outline pixels (left and rightmost of every row) of each 8-connected dark
+/// region.
+fn components(dark: &[bool], w: usize, h: usize) -> Vec<Vec<Vec2>> {
+    let mut seen = vec![false; w * h];
+    let mut out = vec![];
+
+    for start in 0..w * h {
+        if !dark[start] || seen[start] {
+            continue;
+        }
+
+        let mut stack = vec![start];
+        let mut rows: Vec<Option<(usize, usize)>> = vec![None; h];
+        let mut count = 0;
+        seen[start] = true;
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            count += 1;
+            let row = rows[y].get_or_insert((x, x));
+            *row = (row.0.min(x), row.1.max(x));
+
+            for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+                    let n = ny * w + nx;
+                    if dark[n] && !seen[n] {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        if (count as f32) < MIN_SIDE * MIN_SIDE * 0.3 {
+            continue;
+        }
+
+        let outline = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(y, r)| r.map(|r| (y, r)))
+            .flat_map(|(y, (l, r))| [Vec2::new(l as f32, y as f32), Vec2::new(r as f32, y as f32)])
+            .collect();
+        out.push(outline);
+    }
+
+    out
+}
+
+/// Four corners of a region that is close to a convex quadrilateral, clockwise
+/// in the image.
+fn fit_quad(points: &[Vec2]) -> Option<[Vec2; 4]> {
+    let hull = convex_hull(points);
+    if hull.len() < 4 {
+        return None;
+    }
+
+    let centroid = hull.iter().copied().sum::<Vec2>() / hull.len() as f32;
+    let farthest = |from: Vec2, pts: &mut dyn Iterator<Item = Vec2>| {
+        pts.max_by(|a, b| {
+            a.distance_squared(from)
+                .total_cmp(&b.distance_squared(from))
+        })
+    };
+
+    let a = farthest(centroid, &mut hull.iter().copied())?;
+    let c = farthest(a, &mut hull.iter().copied())?;
+    let diagonal = c - a;
+    let side = |p: &Vec2| diagonal.perp_dot(*p - a);
+    let b = hull
+        .iter()
+        .copied()
+        .max_by(|p, q| side(p).total_cmp(&side(q)))?;
+    let d = hull
+        .iter()
+        .copied()
+        .min_by(|p, q| side(p).total_cmp(&side(q)))?;
+
+    // clockwise in image coordinates is counter clockwise with y up
+    let mut quad = [a, b, c, d];
+    if polygon_area(&quad) < 0.0 {
+        quad.swap(1, 3);
+    }
+
+    let quad_area = polygon_area(&quad);
+    if quad_area <= 0.0 || polygon_area(&hull).abs() / quad_area < MIN_QUAD_FILL {
+        return None;
+    }
+    if (0..4).any(|i| quad[i].distance(quad[(i + 1) % 4]) < MIN_SIDE) {
+        return None;
+    }
+
+    // pixel centres sit half a pixel inside the edge
+    let centre = quad.iter().copied().sum::<Vec2>() / 4.0;
+    Some(quad.map(|p| p + (p - centre).normalize_or_zero() * std::f32::consts::FRAC_1_SQRT_2))
+}
+
+/// Moves the corners onto the intersections of lines fitted to the strongest
+/// edge across each side.
+fn refine_quad(img: &GrayImage, quad: [Vec2; 4]) -> [Vec2; 4] {
+    let lines: Option<Vec<(Vec2, Vec2)>> = (0..4)
+        .map(|i| {
+            let (p, q) = (quad[i], quad[(i + 1) % 4]);
+            let along = (q - p).normalize_or_zero();
+            let normal = along.perp();
+
+            let edge: Vec<Vec2> = (0..EDGE_SAMPLES)
+                .map(|k| p + (q - p) * (0.15 + 0.7 * k as f32 / (EDGE_SAMPLES - 1) as f32))
+                .filter_map(|base| {
+                    (-12..=12)
+                        .map(|o| base + normal * (o as f32 * 0.25))
+                        .filter(|x| img.contains(*x, 1.0))
+                        .map(|x| {
+                            let diff = img.sample(x + normal * 0.5) - img.sample(x - normal * 0.5);
+                            (x, diff.abs())
+                        })
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(x, _)| x)
+                })
+                .collect();
+
+            fit_line(&edge)
+        })
+        .collect();
+
+    let Some(lines) = lines else {
+        return quad;
+    };
+
+    let mut refined = quad;
+    for i in 0..4 {
+        let (p1, d1) = lines[(i + 3) % 4];
+        let (p2, d2) = lines[i];
+        let denom = d1.perp_dot(d2);
+        if denom.abs() < 1e-3 {
+            return quad;
+        }
+
+        let corner = p1 + d1 * ((p2 - p1).perp_dot(d2) / denom);
+        if corner.distance(quad[i]) > MAX_CORNER_SHIFT {
+            return quad;
+        }
+        refined[i] = corner;
+    }
+
+    refined
+}
+
+/// Total least squares line through `points` as a point and a direction.
+fn fit_line(points: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    if points.len() < 3 {
+        return None;
+    }
+
+    let mean = points.iter().copied().sum::<Vec2>() / points.len() as f32;
+    let (xx, xy, yy) = points.iter().fold((0.0, 0.0, 0.0), |acc, p| {
+        let d = *p - mean;
+        (acc.0 + d.x * d.x, acc.1 + d.x * d.y, acc.2 + d.y * d.y)
+    });
+    let angle = 0.5 * (2.0 * xy).atan2(xx - yy);
+
+    Some((mean, Vec2::from_angle(angle)))
+}
+
+/// Andrew's monotone chain.
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut pts = points.to_vec();
+    pts.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    pts.dedup();
+
+    if pts.len() < 3 {
+        return pts;
+    }
+
+    let mut hull: Vec<Vec2> = Vec::with_capacity(2 * pts.len());
+    for pass in [pts.clone(), pts.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && (hull[hull.len() - 1] - hull[hull.len() - 2]).perp_dot(p - hull[hull.len() - 2])
+                    <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+
+    hull
+}
+
+/// Signed area, positive for counter clockwise with y up.
+fn polygon_area(points: &[Vec2]) -> f32 {
+    (0..points.len())
+        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Reads the marker inside `quad`, trying each corner as the top left one.
+fn decode(img: &GrayImage, quad: [Vec2; 4]) -> Option<Detection> {
+    let n = MARKER_CELLS as f32;
+    let square = [
+        Vec2::ZERO,
+        Vec2::new(n, 0.0),
+        Vec2::new(n, n),
+        Vec2::new(0.0, n),
+    ];
+    let h = homography(&square, &quad)?;
+    let cells = sample_cells(img, &h);
+
+    let (min, max) = cells
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    if max - min < MIN_CELL_CONTRAST {
+        return None;
+    }
+    let mid = (min + max) / 2.0;
+    let black = |row: usize, col: usize| cells[row * MARKER_CELLS + col] < mid;
+
+    let last = MARKER_CELLS - 1;
+    let border_errors = (0..MARKER_CELLS)
+        .flat_map(|i| [(0, i), (last, i), (i, 0), (i, last)])
+        .filter(|&(r, c)| !black(r, c))
+        .count();
+    if border_errors > MAX_BORDER_ERRORS {
+        return None;
+    }
+
+    let mut code = 0u16;
+    for row in 0..4 {
+        for col in 0..4 {
+            if !black(row + 1, col + 1) {
+                code |= 1 << (15 - (4 * row + col));
+            }
+        }
+    }
+
+    // turning the corners once clockwise turns the code read back once the
+    // other way, so rotate it to undo that
+    let mut corners = quad;
+    for _ in 0..4 {
+        if let Some((id, errors)) = lookup(code) {
+            return Some(Detection {
+                id,
+                corners,
+                errors,
+            });
+        }
+        code = dictionary::rotate(code);
+        corners.rotate_right(1);
+    }
+
+    None
+}
+
+/// Mean intensity around the centre of each cell, row by row.
+fn sample_cells(img: &GrayImage, h: &Matrix3<f64>) -> Vec<f32> {
+    let offsets = [-0.2, 0.0, 0.2];
+    let mut cells = Vec::with_capacity(MARKER_CELLS * MARKER_CELLS);
+
+    for row in 0..MARKER_CELLS {
+        for col in 0..MARKER_CELLS {
+            let mut acc = 0.0;
+            for oy in offsets {
+                for ox in offsets {
+                    let p = h * Vector3::new(col as f64 + 0.5 + ox, row as f64 + 0.5 + oy, 1.0);
+                    acc += img.sample(Vec2::new((p.x / p.z) as f32, (p.y / p.z) as f32));
+                }
+            }
+            cells.push(acc / (offsets.len() * offsets.len()) as f32);
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pastes `marker` into a grey image through the homography `h`.
+    fn warp(marker: &GrayImage, w: usize, hgt: usize, h: Matrix3<f64>) -> GrayImage {
+        let inv = h.try_inverse().unwrap();
+        GrayImage::from_fn(w, hgt, |x, y| {
+            let p = inv * Vector3::new(x as f64, y as f64, 1.0);
+            let (u, v) = (p.x / p.z, p.y / p.z);
+            if u < 0.0 || v < 0.0 || u >= marker.width() as f64 || v >= marker.height() as f64 {
+                0.6
+            } else {
+                marker.sample(Vec2::new(u as f32, v as f32)) * 0.8 + 0.1
+            }
+        })
+    }
+
+    #[test]
+    fn detects_perspective_marker() {
+        let cell = 10;
+        let marker = render_marker(13, cell).unwrap();
+        // rotated by roughly 100 degrees with some perspective
+        let h = Matrix3::new(-0.3, -1.6, 260.0, 1.55, -0.25, 60.0, 0.0004, -0.0003, 1.0);
+        let img = warp(&marker, 320, 240, h);
+
+        let detections = detect_markers(&img);
+        assert_eq!(detections.len(), 1);
+        let det = &detections[0];
+        assert_eq!(det.id, 13);
+        assert_eq!(det.errors, 0);
+
+        // the top left marker corner sits one margin cell into the rendering,
+        // and sampling puts pixel centres on whole coordinates
+        let (a, b) = (cell as f64 - 0.5, 7.0 * cell as f64 - 0.5);
+        let expected = [(a, a), (b, a), (b, b), (a, b)].map(|(u, v)| {
+            let p = h * Vector3::new(u, v, 1.0);
+            Vec2::new((p.x / p.z) as f32, (p.y / p.z) as f32)
+        });
+        for (found, expected) in det.corners.iter().zip(expected) {
+            assert!(found.distance(expected) < 0.5, "{found} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn center_is_diagonal_crossing() {
+        let det = Detection {
+            id: 0,
+            corners: [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(4.0, 0.0),
+                Vec2::new(4.0, 2.0),
+                Vec2::new(0.0, 2.0),
+            ],
+            errors: 0,
+        };
+        assert_eq!(det.center(), Vec2::new(2.0, 1.0));
+    }
+}
@@ -0,0 +1,88 @@
+/// Markers in the dictionary.
+pub const DICTIONARY_SIZE: usize = 30;
+/// Minimum hamming distance between any two markers, in any rotation.
+pub const MIN_DISTANCE: u32 = 5;
+
+/// The AprilTag 16h5 family, as printed by the AprilTag generators. Bit
+/// `15 - (4 * row + col)` is the cell at `row`, `col`, set for white.
+const TAG16H5: [u16; DICTIONARY_SIZE] = [
+    0x231b, 0x2ea5, 0x346a, 0x45b9, 0x79a6, 0x7f6b, 0xb358, 0xe745, 0xfe59, 0x156d, 0x380b, 0xf0ab,
+    0x0d84, 0x4736, 0x8c72, 0xaf10, 0x093c, 0x93b4, 0xa503, 0x468f, 0xe137, 0x5795, 0xdf42, 0x1c1d,
+    0xe9dc, 0x73ad, 0xad5f, 0xd530, 0x07ca, 0xaf2e,
+];
+
+/// Codes of the markers, indexed by id.
+pub fn dictionary() -> &'static [u16] {
+    &TAG16H5
+}
+
+/// Marker id and bit errors of the closest code, if it's close enough to be
+/// corrected.
+pub fn lookup(code: u16) -> Option<(u16, u32)> {
+    dictionary()
+        .iter()
+        .enumerate()
+        .map(|(id, c)| (id as u16, hamming(*c, code)))
+        .min_by_key(|(_, d)| *d)
+        .filter(|(_, d)| *d <= (MIN_DISTANCE - 1) / 2)
+}
+
+pub fn bit(code: u16, row: usize, col: usize) -> bool {
+    code >> (15 - (4 * row + col)) & 1 == 1
+}
+
+/// Code turned a quarter clockwise.
+pub fn rotate(code: u16) -> u16 {
+    let mut out = 0;
+    for row in 0..4 {
+        for col in 0..4 {
+            if bit(code, 3 - col, row) {
+                out |= 1 << (15 - (4 * row + col));
+            }
+        }
+    }
+    out
+}
+
+fn hamming(a: u16, b: u16) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotations(code: u16) -> [u16; 4] {
+        let r1 = rotate(code);
+        let r2 = rotate(r1);
+        [code, r1, r2, rotate(r2)]
+    }
+
+    #[test]
+    fn dictionary_is_well_separated() {
+        let codes = dictionary();
+
+        for (i, a) in codes.iter().enumerate() {
+            assert!(rotations(*a)[1..]
+                .iter()
+                .all(|r| hamming(*a, *r) >= MIN_DISTANCE));
+            for b in &codes[i + 1..] {
+                assert!(rotations(*b)
+                    .iter()
+                    .all(|r| hamming(*a, *r) >= MIN_DISTANCE));
+            }
+        }
+    }
+
+    #[test]
+    fn looks_up_tag16h5_codewords() {
+        // first, middle and last codes of the published family
+        assert_eq!(lookup(0x231b), Some((0, 0)));
+        assert_eq!(lookup(0xaf10), Some((15, 0)));
+        assert_eq!(lookup(0xaf2e), Some((29, 0)));
+
+        assert_eq!(lookup(0x231b ^ 0b1001), Some((0, 2)));
+        assert_eq!(lookup(0x231b ^ 0b1011), None);
+        assert_eq!(lookup(rotate(0x2ea5)), None);
+    }
+}
@@ -0,0 +1,131 @@
+use glam::Vec3A;
+
+use crate::{
+    calibration::Correspondence,
+    config::{CameraProperties, TagAnchor},
+    math::calc_position,
+    ImageCoords,
+};
+
+mod detect;
+mod dictionary;
+
+pub use detect::{detect_markers, render_marker, Detection, MARKER_CELLS};
+pub use dictionary::{dictionary, DICTIONARY_SIZE};
+
+/// A marker seen by both cameras, in world space.
+#[derive(Debug, Clone)]
+pub struct LocatedTag {
+    pub id: u16,
+    pub center: Vec3A,
+    /// Corners in the order of [`Detection::corners`].
+    pub corners: [Vec3A; 4],
+}
+
+impl LocatedTag {
+    /// Mean edge length.
+    pub fn size(&self) -> f32 {
+        (0..4)
+            .map(|i| self.corners[i].distance(self.corners[(i + 1) % 4]))
+            .sum::<f32>()
+            / 4.0
+    }
+}
+
+/// Triangulates the markers found in both cameras.
+pub fn locate_tags(
+    camera1: &CameraProperties,
+    detections1: &[Detection],
+    camera2: &CameraProperties,
+    detections2: &[Detection],
+) -> Vec<LocatedTag> {
+    let coords = |camera: &CameraProperties, p: glam::Vec2| {
+        ImageCoords::new(p.x, p.y, camera.img_width, camera.img_height)
+    };
+    let triangulate =
+        |p1, p2| calc_position(camera1, &coords(camera1, p1), camera2, &coords(camera2, p2)).ok();
+
+    detections1
+        .iter()
+        .filter_map(|d1| {
+            let d2 = detections2.iter().find(|d| d.id == d1.id)?;
+            let center = triangulate(d1.center(), d2.center())?;
+            let corners = [0, 1, 2, 3].map(|i| triangulate(d1.corners[i], d2.corners[i]));
+
+            Some(LocatedTag {
+                id: d1.id,
+                center,
+                corners: [corners[0]?, corners[1]?, corners[2]?, corners[3]?],
+            })
+        })
+        .collect()
+}
+
+/// Pairs the markers at known positions with where they were seen, for
+/// [`crate::calibration::calibrate_extrinsics`].
+pub fn anchor_correspondences(
+    anchors: &[TagAnchor],
+    detections: &[Detection],
+) -> Vec<Correspondence> {
+    detections
+        .iter()
+        .filter_map(|d| {
+            let anchor = anchors.iter().find(|a| a.id == d.id)?;
+
+            Some(Correspondence {
+                name: format!("tag {}", d.id),
+                world: anchor.pos(),
+                pixel: d.center(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::project_local_dir, HasGlamPosition, HasGlamQuat};
+    use glam::Vec2;
+
+    fn camera(pos: Vec3A, yaw: f32) -> CameraProperties {
+        let mut camera = CameraProperties::test_new().with_pose(pos, yaw, 0.0, 0.0);
+        // a real pinhole keeps the diagonals of the tag straight
+        camera.intrinsics = Some(camera.intrinsics_or_fov());
+        camera
+    }
+
+    fn project(camera: &CameraProperties, p: Vec3A) -> Vec2 {
+        project_local_dir(camera, camera.quat().inverse().mul_vec3a(p - *camera.pos())).unwrap()
+    }
+
+    #[test]
+    fn locates_tag_seen_by_both_cameras() {
+        let camera1 = camera(Vec3A::ZERO, 0.2);
+        let camera2 = camera(Vec3A::new(0.0, 3.0, 0.0), -0.6);
+
+        let corners = [
+            Vec3A::new(4.0, 1.0, 0.1),
+            Vec3A::new(4.0, 1.1, 0.1),
+            Vec3A::new(4.0, 1.1, 0.0),
+            Vec3A::new(4.0, 1.0, 0.0),
+        ];
+        let detection = |camera: &CameraProperties| Detection {
+            id: 5,
+            corners: corners.map(|c| project(camera, c)),
+            errors: 0,
+        };
+
+        let tags = locate_tags(
+            &camera1,
+            &[detection(&camera1)],
+            &camera2,
+            &[detection(&camera2)],
+        );
+
+        assert_eq!(tags.len(), 1);
+        assert!(tags[0]
+            .center
+            .abs_diff_eq(Vec3A::new(4.0, 1.05, 0.05), 1e-3));
+        assert!((tags[0].size() - 0.1).abs() < 1e-3);
+    }
+}
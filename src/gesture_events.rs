@@ -0,0 +1,308 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    config::GestureEventsConfig,
+    models::{Gesture, GestureKind},
+    tracking::TrackId,
+};
+
+/// What a person did with their hands in an update.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GestureEvent {
+    Started {
+        gesture: Gesture,
+    },
+    /// The gesture is still being made, with its latest measurements.
+    Held {
+        gesture: Gesture,
+        /// Seconds since it started.
+        duration: f32,
+    },
+    Released {
+        gesture: GestureKind,
+        /// Seconds it was held for.
+        duration: f32,
+    },
+    /// The gesture was started again shortly after being released. Comes
+    /// right after the second `Started`.
+    Double {
+        gesture: GestureKind,
+    },
+    /// The gestures of a configured sequence were started one after another.
+    Sequence {
+        name: String,
+    },
+}
+
+impl fmt::Display for GestureEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Started { gesture } => write!(f, "started {gesture}"),
+            Self::Held { gesture, duration } => write!(f, "holding {gesture} for {duration:.1}s"),
+            Self::Released { gesture, duration } => {
+                write!(f, "released {gesture} after {duration:.1}s")
+            }
+            Self::Double { gesture } => write!(f, "double {gesture}"),
+            Self::Sequence { name } => write!(f, "sequence {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Hands {
+    /// Seconds since the person was first seen.
+    clock: f32,
+    /// Gesture being held and when it started.
+    active: Option<(Gesture, f32)>,
+    /// A different gesture than the active one, the frames in a row it's
+    /// been seen and when it was first seen.
+    candidate: Option<(GestureKind, usize, f32)>,
+    /// Last gesture released and when.
+    released: Option<(GestureKind, f32)>,
+    /// Gestures started lately, each within the sequence timeout of the
+    /// previous one.
+    started: Vec<(GestureKind, f32)>,
+}
+
+/// Debounces the gesture a person makes in each frame into gestures that
+/// start, are held and released, and spots doubles and sequences of them.
+#[derive(Debug)]
+pub struct GestureEvents {
+    config: GestureEventsConfig,
+    hands: HashMap<TrackId, Hands>,
+}
+
+impl GestureEvents {
+    pub fn new(config: GestureEventsConfig) -> Self {
+        Self {
+            config,
+            hands: HashMap::new(),
+        }
+    }
+
+    /// Gesture `person` is holding.
+    pub fn active(&self, person: TrackId) -> Option<&Gesture> {
+        self.hands.get(&person)?.active.as_ref().map(|(g, _)| g)
+    }
+
+    /// Feeds the gesture `person` made `dt` seconds after the last update,
+    /// `Gesture::None` if they made none or weren't seen.
+    pub fn update(&mut self, person: TrackId, gesture: Gesture, dt: f32) -> Vec<GestureEvent> {
+        let config = &self.config;
+        let hands = self.hands.entry(person).or_default();
+        hands.clock += dt;
+        let now = hands.clock;
+        let kind = gesture.kind();
+        let active_kind = hands
+            .active
+            .as_ref()
+            .map_or(GestureKind::None, |(g, _)| g.kind());
+
+        let mut events = vec![];
+        if kind == active_kind {
+            hands.candidate = None;
+        } else {
+            let (streak, since) = match &hands.candidate {
+                Some((candidate, streak, since)) if *candidate == kind => (streak + 1, *since),
+                _ => (1, now),
+            };
+            hands.candidate = Some((kind.clone(), streak, since));
+
+            if streak >= config.min_frames.max(1) {
+                hands.candidate = None;
+                if let Some((held, started)) = hands.active.take() {
+                    events.push(GestureEvent::Released {
+                        gesture: held.kind(),
+                        duration: since - started,
+                    });
+                    hands.released = Some((held.kind(), since));
+                }
+                if !gesture.is_none() {
+                    hands.active = Some((gesture.clone(), since));
+                    events.push(GestureEvent::Started { gesture });
+                    events.extend(Self::started(config, hands, kind, since));
+                }
+                return events;
+            }
+        }
+
+        // a gesture missed for fewer than `min_frames` is still held
+        if let Some((held, started)) = &mut hands.active {
+            if kind == active_kind {
+                *held = gesture;
+            }
+            events.push(GestureEvent::Held {
+                gesture: held.clone(),
+                duration: now - *started,
+            });
+        }
+
+        events
+    }
+
+    /// Doubles and sequences completed by starting `kind` at `at`.
+    fn started(
+        config: &GestureEventsConfig,
+        hands: &mut Hands,
+        kind: GestureKind,
+        at: f32,
+    ) -> Vec<GestureEvent> {
+        let mut events = vec![];
+
+        if let Some((released, when)) = &hands.released {
+            if *released == kind && at - when <= config.double_timeout {
+                events.push(GestureEvent::Double {
+                    gesture: kind.clone(),
+                });
+                hands.released = None;
+            }
+        }
+
+        if hands
+            .started
+            .last()
+            .is_some_and(|(_, when)| at - when > config.sequence_timeout)
+        {
+            hands.started.clear();
+        }
+        hands.started.push((kind, at));
+
+        let started: Vec<GestureKind> = hands.started.iter().map(|(k, _)| k.clone()).collect();
+        if let Some(sequence) = config
+            .sequences
+            .iter()
+            .find(|s| !s.gestures.is_empty() && started.ends_with(&s.gestures))
+        {
+            events.push(GestureEvent::Sequence {
+                name: sequence.name.clone(),
+            });
+            hands.started.clear();
+        }
+
+        events
+    }
+
+    /// Drops the gestures of a person who's gone.
+    pub fn forget(&mut self, person: TrackId) {
+        self.hands.remove(&person);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GestureSequence, models::SwipeDirection};
+
+    const DT: f32 = 0.125;
+
+    fn events() -> GestureEvents {
+        GestureEvents::new(GestureEventsConfig {
+            min_frames: 3,
+            double_timeout: 0.5,
+            sequence_timeout: 1.0,
+            sequences: vec![GestureSequence {
+                name: "next".to_owned(),
+                gestures: vec![GestureKind::Fist, GestureKind::Swipe(SwipeDirection::Right)],
+            }],
+        })
+    }
+
+    fn run(events: &mut GestureEvents, gesture: Gesture, frames: usize) -> Vec<GestureEvent> {
+        (0..frames)
+            .flat_map(|_| events.update(TrackId(0), gesture.clone(), DT))
+            .collect()
+    }
+
+    #[test]
+    fn debounces_holds_and_releases() {
+        let mut events = events();
+
+        // too short to start
+        assert!(run(&mut events, Gesture::Fist, 2).is_empty());
+        assert!(run(&mut events, Gesture::None, 1).is_empty());
+
+        let started = run(&mut events, Gesture::Fist, 4);
+        assert_eq!(
+            started,
+            [
+                GestureEvent::Started {
+                    gesture: Gesture::Fist
+                },
+                GestureEvent::Held {
+                    gesture: Gesture::Fist,
+                    duration: 3.0 * DT
+                }
+            ]
+        );
+
+        // one missed frame doesn't end it
+        assert!(matches!(
+            run(&mut events, Gesture::None, 1)[..],
+            [GestureEvent::Held { .. }]
+        ));
+        run(&mut events, Gesture::Fist, 1);
+
+        let released = run(&mut events, Gesture::None, 3);
+        assert_eq!(
+            released.last(),
+            Some(&GestureEvent::Released {
+                gesture: GestureKind::Fist,
+                duration: 6.0 * DT
+            })
+        );
+        assert_eq!(events.active(TrackId(0)), None);
+    }
+
+    #[test]
+    fn spots_doubles_and_sequences() {
+        let mut events = events();
+        let swipe = Gesture::Swipe {
+            direction: SwipeDirection::Right,
+            velocity: 1.0,
+        };
+
+        run(&mut events, Gesture::Point, 4);
+        run(&mut events, Gesture::None, 3);
+        let again = run(&mut events, Gesture::Point, 3);
+        assert!(again.contains(&GestureEvent::Double {
+            gesture: GestureKind::Point
+        }));
+
+        // switching straight from one gesture to the next
+        run(&mut events, Gesture::Fist, 3);
+        let next = run(&mut events, swipe.clone(), 3);
+        assert_eq!(
+            next,
+            [
+                GestureEvent::Held {
+                    gesture: Gesture::Fist,
+                    duration: 3.0 * DT
+                },
+                GestureEvent::Held {
+                    gesture: Gesture::Fist,
+                    duration: 4.0 * DT
+                },
+                GestureEvent::Released {
+                    gesture: GestureKind::Fist,
+                    duration: 3.0 * DT
+                },
+                GestureEvent::Started {
+                    gesture: swipe.clone()
+                },
+                GestureEvent::Sequence {
+                    name: "next".to_owned()
+                },
+            ]
+        );
+
+        // too slow for a sequence or a double
+        run(&mut events, Gesture::None, 3);
+        run(&mut events, Gesture::Fist, 3);
+        run(&mut events, Gesture::None, 10);
+        let late = run(&mut events, swipe.clone(), 3);
+        assert_eq!(late, [GestureEvent::Started { gesture: swipe }]);
+
+        events.forget(TrackId(0));
+        assert_eq!(events.active(TrackId(0)), None);
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+
+use error_stack::{Result, ResultExt};
+use glam::Vec2;
+
+use crate::GError;
+
+/// Single channel image with intensities in `0.0..=1.0`.
+#[derive(Clone, Debug)]
+pub struct GrayImage {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl GrayImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0.0; width * height],
+        }
+    }
+
+    pub fn from_fn(width: usize, height: usize, f: impl Fn(usize, usize) -> f32) -> Self {
+        let mut img = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                img.data[y * width + x] = f(x, y);
+            }
+        }
+        img
+    }
+
+    pub fn open(path: &Path) -> Result<Self, GError> {
+        let img = image::open(path)
+            .change_context(GError::ImageError)
+            .attach_printable_lazy(|| format!("Couldn't open image {}", path.display()))?
+            .into_luma8();
+
+        Ok(Self::from_luma8(
+            img.width() as usize,
+            img.height() as usize,
+            img.as_raw(),
+        ))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GError> {
+        let data = self
+            .data
+            .iter()
+            .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+
+        image::GrayImage::from_raw(self.width as u32, self.height as u32, data)
+            .ok_or(GError::ImageError)
+            .attach_printable("Image buffer has the wrong size")?
+            .save(path)
+            .change_context(GError::ImageError)
+            .attach_printable_lazy(|| format!("Couldn't save image {}", path.display()))
+    }
+
+    pub fn from_luma8(width: usize, height: usize, data: &[u8]) -> Self {
+        Self {
+            width,
+            height,
+            data: data.iter().map(|&v| v as f32 / 255.0).collect(),
+        }
+    }
+
+    /// Converts a raw packed frame as sent by the camera process. The number of
+    /// channels is inferred from the buffer length.
+    pub fn from_raw_frame(frame: &[u8], width: u32, height: u32) -> Result<Self, GError> {
+        let (width, height) = (width as usize, height as usize);
+        let pixels = width * height;
+
+        if pixels == 0 || !frame.len().is_multiple_of(pixels) {
+            return Err(GError::ImageError).attach_printable(format!(
+                "Frame of {} bytes doesn't match a {}x{} image",
+                frame.len(),
+                width,
+                height
+            ));
+        }
+
+        let channels = frame.len() / pixels;
+        let data = frame
+            .chunks_exact(channels)
+            .map(|px| match channels {
+                1 | 2 => px[0] as f32 / 255.0,
+                _ => (0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32) / 255.0,
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.data[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, v: f32) {
+        self.data[y * self.width + x] = v;
+    }
+
+    /// Bilinearly interpolated intensity, clamped at the borders.
+    pub fn sample(&self, p: Vec2) -> f32 {
+        let x = p.x.clamp(0.0, (self.width - 1) as f32);
+        let y = p.y.clamp(0.0, (self.height - 1) as f32);
+
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+        let top = self.get(x0, y0) * (1.0 - fx) + self.get(x1, y0) * fx;
+        let bottom = self.get(x0, y1) * (1.0 - fx) + self.get(x1, y1) * fx;
+
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    pub fn contains(&self, p: Vec2, margin: f32) -> bool {
+        p.x >= margin
+            && p.y >= margin
+            && p.x <= self.width as f32 - 1.0 - margin
+            && p.y <= self.height as f32 - 1.0 - margin
+    }
+
+    /// Draws a one pixel wide line, skipping the parts outside the image.
+    pub fn draw_line(&mut self, a: Vec2, b: Vec2, v: f32) {
+        let steps = (b - a).abs().max_element().ceil().min(1e5) as usize;
+        for i in 0..=steps {
+            let p = a.lerp(b, i as f32 / steps.max(1) as f32).round();
+            if p.x >= 0.0 && p.y >= 0.0 && p.x < self.width as f32 && p.y < self.height as f32 {
+                self.set(p.x as usize, p.y as usize, v);
+            }
+        }
+    }
+
+    /// Separable gaussian blur.
+    pub fn blur(&self, sigma: f32) -> Self {
+        let radius = (3.0 * sigma).ceil() as isize;
+        let kernel: Vec<f32> = (-radius..=radius)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let norm: f32 = kernel.iter().sum();
+
+        let convolve = |src: &Self, horizontal: bool| {
+            let mut out = Self::new(src.width, src.height);
+            for y in 0..src.height {
+                for x in 0..src.width {
+                    let mut acc = 0.0;
+                    for (k, w) in kernel.iter().enumerate() {
+                        let o = k as isize - radius;
+                        let (sx, sy) = if horizontal {
+                            (
+                                (x as isize + o).clamp(0, src.width as isize - 1) as usize,
+                                y,
+                            )
+                        } else {
+                            (
+                                x,
+                                (y as isize + o).clamp(0, src.height as isize - 1) as usize,
+                            )
+                        };
+                        acc += w * src.get(sx, sy);
+                    }
+                    out.set(x, y, acc / norm);
+                }
+            }
+            out
+        };
+
+        convolve(&convolve(self, true), false)
+    }
+}
@@ -6,17 +6,22 @@ use std::{
     fmt,
     io::Read,
     os::unix::net::{UnixListener, UnixStream},
-    usize,
 };
 
 use models::{GestureDetection, HeadDetection, HeadPoseEstimation};
 
 mod error;
 
+pub mod calibration;
 pub mod camera;
 pub mod config;
+pub mod fiducial;
+pub mod gesture_events;
+pub mod imgproc;
 pub mod math;
 pub mod models;
+pub mod selection;
+pub mod tracking;
 pub mod traits;
 
 pub use error::GError;
@@ -149,7 +154,7 @@ impl Models {
     pub fn add_process(&mut self, model: Process, stream: UnixStream, config: &Config) {
         match model {
             Process::HPE => {
-                let model = HeadPoseEstimation::new(stream);
+                let model = HeadPoseEstimation::new(stream, config.models.hpe.angles);
 
                 model.run();
 
@@ -190,18 +195,44 @@ impl Models {
         self.pset.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.pset.is_empty()
+    }
+
     pub fn wait_for_connection(&mut self, config: &Config) {
         while self.len() < self.num {
-            let (mut stream, _addr) = self.listener.accept().unwrap();
+            let (model, stream) = self.accept();
+
+            self.add_process(model, stream, config);
+            println!("Processes connected: {}", self.len())
+        }
+    }
 
-            let mut buffer = [0; 1024];
-            let bytes_read = stream.read(&mut buffer).unwrap();
-            let model: Process = String::from_utf8_lossy(&buffer[..bytes_read])
-                .as_ref()
-                .into();
+    /// Accepts connections until `process` is connected, turning away any
+    /// other process that connects first.
+    pub fn wait_for_process(&mut self, process: Process, config: &Config) {
+        while !self.pset.contains(&process) {
+            let (model, stream) = self.accept();
+
+            if model != process {
+                println!("Turning away {model}, waiting for {process}");
+                continue;
+            }
 
             self.add_process(model, stream, config);
             println!("Processes connected: {}", self.len())
         }
     }
+
+    fn accept(&self) -> (Process, UnixStream) {
+        let (mut stream, _addr) = self.listener.accept().unwrap();
+
+        let mut buffer = [0; 1024];
+        let bytes_read = stream.read(&mut buffer).unwrap();
+        let model: Process = String::from_utf8_lossy(&buffer[..bytes_read])
+            .as_ref()
+            .into();
+
+        (model, stream)
+    }
 }
@@ -1,34 +1,446 @@
+use std::collections::BTreeMap;
 use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 
-use gesture_ease::config::Config;
+use clap::{Parser, Subcommand};
+use error_stack::ResultExt;
+use gesture_ease::calibration::{calibrate_dir, calibrate_extrinsics, Board, LensModel, Survey};
+use gesture_ease::config::{Config, ConfigEditor, SelectionMode, TargetCue, TargetingMode};
+use gesture_ease::fiducial::{anchor_correspondences, detect_markers, locate_tags, render_marker};
+use gesture_ease::gesture_events::{GestureEvent, GestureEvents};
+use gesture_ease::imgproc::GrayImage;
 use gesture_ease::math::{
-    angle_bw_cameras_from_z_axis, calc_position, get_closest_device_in_los, get_los, sort_align,
+    associate, associate_in_image, device_visibility, devices_in_cone, fuse_cues, fuse_head_poses,
+    gaze_line, get_closest_device_in_los, pointing_ray, pointing_uncertainty, project_shape,
+    target_probabilities, triangulate_arm, triangulation_covariance, Cue, Visibility,
 };
-use gesture_ease::models::{GesturePreds, HPEPreds, HeadPreds};
-use gesture_ease::{GError, HasGlamQuat, HasImagePosition, Models};
+use gesture_ease::models::{Gesture, GesturePreds, HPEPreds, HandPoseClassifier, HeadPreds};
+use gesture_ease::selection::DwellSelector;
+use gesture_ease::tracking::{Observation, Tracker};
+use gesture_ease::{GError, HasGlamQuat, HasImagePosition, Models, Process};
+use glam::Mat3A;
+
+const SOCKET_PATH: &str = "/tmp/gesurease.sock";
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Path to the config file
+    #[arg(long, default_value = "config.toml")]
+    config: PathBuf,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the gesture pipeline (default)
+    Run,
+    /// Calibrate a camera's intrinsics and distortion from checkerboard images
+    Calibrate {
+        /// Config table of the camera, e.g. camera1
+        #[arg(long)]
+        camera: String,
+        /// Directory with the checkerboard images
+        dir: PathBuf,
+        /// Inner corners along a row of the board
+        #[arg(long)]
+        cols: usize,
+        /// Inner corners along a column of the board
+        #[arg(long)]
+        rows: usize,
+        /// Edge length of a square
+        #[arg(long, default_value_t = 1.0)]
+        square: f32,
+        /// Fit a fisheye instead of a Brown-Conrady distortion model
+        #[arg(long)]
+        fisheye: bool,
+        /// Print the result without writing it to the config
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Solve camera poses from surveyed world points and their pixel positions
+    Extrinsics {
+        /// Survey file with the points and their pixels per camera
+        survey: PathBuf,
+        /// Print the result without writing it to the config
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write a printable AprilTag 16h5 fiducial marker image
+    Marker {
+        /// Marker id, from 0 to 29
+        id: u16,
+        /// Output image
+        out: PathBuf,
+        /// Pixels per marker cell
+        #[arg(long, default_value_t = 40)]
+        cell: usize,
+    },
+    /// Locate fiducial markers from the live cameras, solving camera poses from
+    /// `[[tags]]` anchors and moving devices onto their tags
+    Localise {
+        /// Frames to collect markers from
+        #[arg(long, default_value_t = 5)]
+        frames: usize,
+        /// Print the result without writing it to the config
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Save a frame from each camera with the configured devices and occluders
+    /// drawn on top
+    Overlay {
+        /// Directory to write camera1.png and camera2.png to
+        #[arg(default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// Train the hand pose classifier from recorded hands, set
+    /// `[hand_poses] model` to the output to use it
+    TrainHands {
+        /// Directory with a subdirectory of JSON recordings per pose
+        dir: PathBuf,
+        /// Where to write the classifier
+        #[arg(long, default_value = "hand_poses.json")]
+        out: PathBuf,
+        /// Nearest examples that vote on a pose
+        #[arg(long, default_value_t = 5)]
+        k: usize,
+    },
+}
 
 fn main() {
-    let socket_path = "/tmp/gesurease.sock";
-    let num_processes = 4;
+    let cli = Cli::parse();
 
-    if std::fs::metadata(socket_path).is_ok() {
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(cli.config),
+        Command::Calibrate {
+            camera,
+            dir,
+            cols,
+            rows,
+            square,
+            fisheye,
+            dry_run,
+        } => {
+            let board = Board { cols, rows, square };
+            let model = if fisheye {
+                LensModel::Fisheye
+            } else {
+                LensModel::BrownConrady
+            };
+            calibrate(cli.config, &camera, dir, board, model, dry_run).unwrap()
+        }
+        Command::Extrinsics { survey, dry_run } => extrinsics(cli.config, survey, dry_run).unwrap(),
+        Command::Marker { id, out, cell } => marker(id, out, cell).unwrap(),
+        Command::Localise { frames, dry_run } => localise(cli.config, frames, dry_run).unwrap(),
+        Command::Overlay { out_dir } => overlay(cli.config, out_dir).unwrap(),
+        Command::TrainHands { dir, out, k } => train_hands(dir, out, k).unwrap(),
+    }
+}
+
+fn bind_socket() -> UnixListener {
+    if std::fs::metadata(SOCKET_PATH).is_ok() {
         println!("Socket is already present. Deleting...");
-        std::fs::remove_file(socket_path).unwrap();
+        std::fs::remove_file(SOCKET_PATH).unwrap();
+    }
+
+    UnixListener::bind(SOCKET_PATH).unwrap()
+}
+
+/// Connects to the camera process alone, for commands that only need frames.
+fn connect_cameras(config: &Config) -> Models {
+    let mut process_map = Models::new(1, bind_socket());
+    println!("Waiting for the camera process");
+    process_map.wait_for_process(Process::Camera, config);
+    process_map
+}
+
+fn calibrate(
+    config_path: PathBuf,
+    camera: &str,
+    dir: PathBuf,
+    board: Board,
+    model: LensModel,
+    dry_run: bool,
+) -> error_stack::Result<(), GError> {
+    let config = Config::open(config_path.clone())?;
+    let properties = match camera {
+        "camera1" => &config.camera1,
+        "camera2" => &config.camera2,
+        _ => {
+            return Err(GError::ConfigError).attach_printable(format!(
+                "Unknown camera {camera}, expected camera1 or camera2"
+            ))
+        }
+    };
+
+    let result = calibrate_dir(&dir, &board, model)?;
+    let calib = &result.calibration;
+
+    for path in &result.skipped {
+        println!("board not found in {}", path.display());
+    }
+    for (path, rms) in result.used.iter().zip(&calib.per_view_rms) {
+        println!("{}: {:.3} px", path.display(), rms);
+    }
+    println!(
+        "calibrated from {} images, reprojection error {:.3} px",
+        result.used.len(),
+        calib.rms
+    );
+
+    let intrinsics = calib.intrinsics_for(properties.img_width, properties.img_height);
+    if calib.image_size != (properties.img_width, properties.img_height) {
+        println!(
+            "images are {}x{}, rescaled intrinsics to {}x{}",
+            calib.image_size.0, calib.image_size.1, properties.img_width, properties.img_height
+        );
+    }
+    println!("{:?}\n{:?}", intrinsics, calib.distortion);
+
+    if !dry_run {
+        let mut editor = ConfigEditor::open(config_path)?;
+        editor.set_intrinsics(camera, &intrinsics, &calib.distortion)?;
+        editor.save()?;
+        println!("written to [{camera}]");
+    }
+
+    Ok(())
+}
+
+fn extrinsics(
+    config_path: PathBuf,
+    survey: PathBuf,
+    dry_run: bool,
+) -> error_stack::Result<(), GError> {
+    let config = Config::open(config_path.clone())?;
+    let survey = Survey::open(&survey)?;
+    let mut editor = ConfigEditor::open(config_path)?;
+
+    for (name, camera) in [("camera1", &config.camera1), ("camera2", &config.camera2)] {
+        let points = survey.correspondences(name);
+        if points.is_empty() {
+            continue;
+        }
+
+        let result = calibrate_extrinsics(camera, &points)?;
+        let (yaw, pitch, roll) = result.euler();
+
+        println!("[{name}]");
+        for r in &result.residuals {
+            match r.pixels {
+                Some(px) => println!(
+                    "  {}: {:.2} px, {:.3} deg",
+                    r.name,
+                    px,
+                    r.angle.to_degrees()
+                ),
+                None => println!("  {}: behind camera", r.name),
+            }
+        }
+        println!(
+            "  pos = {}, yaw = {yaw}, pitch = {pitch}, roll = {roll}, rms {:.2} px",
+            result.pos,
+            result.rms()
+        );
+
+        editor.set_pose(name, result.pos, yaw, pitch, roll)?;
+    }
+
+    if !dry_run {
+        editor.save()?;
+        println!("written to the config");
+    }
+
+    Ok(())
+}
+
+fn marker(id: u16, out: PathBuf, cell: usize) -> error_stack::Result<(), GError> {
+    render_marker(id, cell)
+        .ok_or(GError::ImageError)
+        .attach_printable(format!("No marker with id {id}"))?
+        .save(&out)?;
+
+    println!("marker {id} written to {}", out.display());
+    Ok(())
+}
+
+fn train_hands(dir: PathBuf, out: PathBuf, k: usize) -> error_stack::Result<(), GError> {
+    let classifier = HandPoseClassifier::train_dir(&dir, k)?;
+    for (label, count) in classifier.labels() {
+        println!("{label}: {count} examples");
+    }
+
+    classifier.save(&out)?;
+    println!("hand pose classifier written to {}", out.display());
+    Ok(())
+}
+
+fn localise(config_path: PathBuf, frames: usize, dry_run: bool) -> error_stack::Result<(), GError> {
+    let config = Config::open(config_path.clone())?;
+    let mut editor = ConfigEditor::open(config_path)?;
+
+    let process_map = connect_cameras(&config);
+
+    let mut cameras = [config.camera1.clone(), config.camera2.clone()];
+    let mut seen = [vec![], vec![]];
+    for _ in 0..frames {
+        let frame = process_map.cams()?.get()?;
+        for (i, data) in [frame.cam1, frame.cam2].iter().enumerate() {
+            let img = GrayImage::from_raw_frame(data, cameras[i].img_width, cameras[i].img_height)?;
+            seen[i].push(detect_markers(&img));
+        }
+    }
+
+    for (i, name) in ["camera1", "camera2"].into_iter().enumerate() {
+        let points: Vec<_> = seen[i]
+            .iter()
+            .flat_map(|detections| anchor_correspondences(&config.tags, detections))
+            .collect();
+        let mut anchors: Vec<&str> = points.iter().map(|p| p.name.as_str()).collect();
+        anchors.sort();
+        anchors.dedup();
+
+        if anchors.len() < 4 {
+            println!(
+                "[{name}] sees {} anchor tags, keeping its pose",
+                anchors.len()
+            );
+            continue;
+        }
+
+        let result = calibrate_extrinsics(&cameras[i], &points)?;
+        let (yaw, pitch, roll) = result.euler();
+        println!(
+            "[{name}] pos = {}, yaw = {yaw}, pitch = {pitch}, roll = {roll}, rms {:.2} px",
+            result.pos,
+            result.rms()
+        );
+
+        cameras[i] = cameras[i].with_pose(result.pos, yaw, pitch, roll);
+        editor.set_pose(name, result.pos, yaw, pitch, roll)?;
+    }
+
+    let mut located: BTreeMap<u16, Vec<_>> = BTreeMap::new();
+    for (d1, d2) in seen[0].iter().zip(&seen[1]) {
+        for tag in locate_tags(&cameras[0], d1, &cameras[1], d2) {
+            located.entry(tag.id).or_default().push(tag.center);
+        }
+    }
+    let centers: BTreeMap<u16, _> = located
+        .into_iter()
+        .map(|(id, c)| (id, c.iter().copied().sum::<glam::Vec3A>() / c.len() as f32))
+        .collect();
+
+    for (id, center) in &centers {
+        println!("tag {id} at {center}");
+    }
+
+    for device in &config.devices {
+        let Some(tag) = device.tag else {
+            continue;
+        };
+
+        match centers.get(&tag) {
+            Some(center) => {
+                let shape = device.recentered(*center);
+                println!("{} placed at {center}", device.name);
+                editor.set_device_shape(&device.name, &shape)?;
+            }
+            None => println!("tag {tag} of {} wasn't seen by both cameras", device.name),
+        }
+    }
+
+    if !dry_run {
+        editor.save()?;
+        println!("written to the config");
+    }
+
+    Ok(())
+}
+
+/// Lists the devices each camera can and can't see.
+fn report_fov(config: &Config) {
+    for (name, camera) in [("camera1", &config.camera1), ("camera2", &config.camera2)] {
+        let mut partial = vec![];
+        let mut outside = vec![];
+        for device in &config.devices {
+            match device_visibility(camera, device) {
+                Visibility::Inside => {}
+                Visibility::Partial => partial.push(device.name.as_str()),
+                Visibility::Outside => outside.push(device.name.as_str()),
+            }
+        }
+
+        let seen = config.devices.len() - outside.len();
+        println!("[{name}] sees {seen} of {} devices", config.devices.len());
+        if !partial.is_empty() {
+            println!("[{name}] partly out of view: {}", partial.join(", "));
+        }
+        if !outside.is_empty() {
+            println!("[{name}] out of view: {}", outside.join(", "));
+        }
+    }
+}
+
+fn overlay(config_path: PathBuf, out_dir: PathBuf) -> error_stack::Result<(), GError> {
+    let config = Config::open(config_path)?;
+    report_fov(&config);
+
+    let process_map = connect_cameras(&config);
+
+    let frame = process_map.cams()?.get()?;
+    let cameras = [("camera1", &config.camera1), ("camera2", &config.camera2)];
+    for ((name, camera), data) in cameras.into_iter().zip([frame.cam1, frame.cam2]) {
+        let mut img = GrayImage::from_raw_frame(&data, camera.img_width, camera.img_height)?;
+
+        // devices in white, occluders in black
+        let shapes = config
+            .devices
+            .iter()
+            .map(|d| (&d.shape, 1.0))
+            .chain(config.occluders.iter().map(|o| (&o.shape, 0.0)));
+        for (shape, v) in shapes {
+            for (a, b) in project_shape(camera, shape).edges {
+                img.draw_line(a, b, v);
+            }
+        }
+
+        let path = out_dir.join(format!("{name}.png"));
+        img.save(&path)?;
+        println!("[{name}] written to {}", path.display());
     }
 
-    let config = Config::open("config.toml".into()).unwrap();
+    Ok(())
+}
+
+fn run(config_path: PathBuf) {
+    let num_processes = 4;
 
-    let listener = UnixListener::bind(socket_path).unwrap();
-    let mut process_map = Models::new(num_processes, listener);
+    let config = Config::open(config_path).unwrap();
+    report_fov(&config);
+    let hand_poses = config
+        .hand_poses
+        .model
+        .as_ref()
+        .map(|path| HandPoseClassifier::open(path).unwrap());
 
-    let theta = angle_bw_cameras_from_z_axis(&config.camera1, &config.camera2);
+    let mut process_map = Models::new(num_processes, bind_socket());
 
     let mut headposes: HPEPreds = Default::default();
+    let mut headposes2: HPEPreds = Default::default();
     let mut gestures: GesturePreds = Default::default();
+    let mut gestures2: GesturePreds = Default::default();
     let mut head_positions: HeadPreds = Default::default();
 
+    let mut tracker = Tracker::new(config.tracking.clone(), config.smoothing.clone());
+    let mut dwell = DwellSelector::new(config.selection.clone());
+    let mut gesture_events = GestureEvents::new(config.gesture_events.clone());
+    let mut last_frame = Instant::now();
+
     process_map.wait_for_connection(&config);
 
     let mut run = || -> error_stack::Result<(), GError> {
@@ -50,65 +462,229 @@ fn main() {
             config.camera2.img_height,
         )?;
 
+        // the arm keypoints have to be seen by both cameras to point with
+        let pointing = config.pointing.cue != TargetCue::Gaze;
+        if pointing {
+            process_map.gesture()?.send(
+                frame2.clone(),
+                config.camera2.img_width,
+                config.camera2.img_height,
+            )?;
+        }
+
         head_positions = process_map.head_detection()?.recv()?;
         gestures = process_map.gesture()?.recv()?;
+        gestures2 = if pointing {
+            process_map.gesture()?.recv()?
+        } else {
+            Default::default()
+        };
+
+        // poses of our own classifier stand in where the model saw nothing
+        if let Some(classifier) = &hand_poses {
+            for prediction in gestures.iter_mut().filter(|p| p.is_none()) {
+                if let Some(pose) = prediction
+                    .best_hand()
+                    .and_then(|hand| classifier.classify(hand, &config.hand_poses))
+                {
+                    prediction.gesture = Gesture::Custom(pose.label);
+                }
+            }
+        }
 
-        // check if any gesture is not none
-        if true
-            || gestures
-                .iter()
-                .map(|x| &x.gesture)
-                .find(|x| x.is_toggle())
-                .is_some()
-        {
-            // send frame1 to hpe model
+        // head poses are needed on every frame, dwell selection and tracking
+        // don't wait for a gesture
+        process_map.hpe()?.send(
+            frame1.clone(),
+            config.camera1.img_width,
+            config.camera1.img_height,
+        )?;
+        let hpe_all_cameras = config.models.hpe.all_cameras;
+        if hpe_all_cameras {
             process_map.hpe()?.send(
-                frame1.clone(),
-                config.camera1.img_width,
-                config.camera1.img_height,
+                frame2.clone(),
+                config.camera2.img_width,
+                config.camera2.img_height,
             )?;
+        }
+
+        // in the meantime work out which heads of camera 2 belong to the
+        // people in camera 1
+        let association = associate(
+            &config.camera1,
+            &gestures,
+            &config.camera2,
+            &head_positions,
+            &config.triangulation,
+            &config.room,
+        );
+
+        for i in &association.unmatched1 {
+            if !gestures[*i].is_none() {
+                match association.rejected1.iter().find(|(j, _)| j == i) {
+                    Some((_, rejection)) => eprintln!(
+                        "gesture {} has no matching head in camera 2, closest: {rejection}",
+                        gestures[*i].gesture
+                    ),
+                    None => eprintln!(
+                        "gesture {} has no matching head in camera 2",
+                        gestures[*i].gesture
+                    ),
+                }
+            }
+        }
+        for m in &association.matched {
+            if let Some(reason) = m.triangulation.outside_room {
+                eprintln!("head clamped into the room, it was {reason}");
+            }
+        }
 
-            sort_align(&mut head_positions, theta);
-            sort_align(&mut gestures, theta);
-            // in the meantime calculate positition of head which had a gesture
-            let positions = gestures.iter().zip(head_positions.iter()).map(|(g, h)| {
-                if !g.is_none() {
-                    Some((
-                        calc_position(
+        headposes = process_map.hpe()?.recv()?;
+        headposes2 = if hpe_all_cameras {
+            process_map.hpe()?.recv()?
+        } else {
+            Default::default()
+        };
+        let max_offset1 = config.models.max_pair_pixels(&config.camera1);
+        let max_offset2 = config.models.max_pair_pixels(&config.camera2);
+        let poses = associate_in_image(&gestures, &headposes, max_offset1);
+        let poses2 = associate_in_image(&head_positions, &headposes2, max_offset2);
+        let arms2 = associate_in_image(&head_positions, &gestures2, max_offset2);
+
+        let observations = association
+            .matched
+            .iter()
+            .map(|m| Observation {
+                position: m.triangulation.point,
+                position_covariance: triangulation_covariance(
+                    &[
+                        (
                             &config.camera1,
-                            &g.image_coords(config.camera1.img_width, config.camera1.img_height),
+                            &gestures[m.index1]
+                                .image_coords(config.camera1.img_width, config.camera1.img_height),
+                        ),
+                        (
                             &config.camera2,
-                            &h.image_coords(config.camera2.img_width, config.camera2.img_height),
-                        )
-                        .unwrap(),
-                        g.gesture.clone(),
-                    ))
-                } else {
-                    None
-                }
-            });
+                            &head_positions[m.index2]
+                                .image_coords(config.camera2.img_width, config.camera2.img_height),
+                        ),
+                    ],
+                    config.uncertainty.pixel_noise,
+                ),
+                gesture: gestures[m.index1].gesture.clone(),
+                head_orientation: {
+                    let view1 = poses
+                        .iter()
+                        .find(|(g, _)| *g == m.index1)
+                        .map(|(_, p)| (&config.camera1, headposes[*p].quat()));
+                    let view2 = poses2
+                        .iter()
+                        .find(|(h, _)| *h == m.index2)
+                        .map(|(_, p)| (&config.camera2, headposes2[*p].quat()));
+                    let views: Vec<_> = view1.into_iter().chain(view2).collect();
+                    fuse_head_poses(&views, m.triangulation.point)
+                },
+                pointing: arms2
+                    .iter()
+                    .find(|(h, _)| *h == m.index2)
+                    .and_then(|(_, g)| {
+                        let arm = triangulate_arm(
+                            &config.camera1,
+                            &gestures[m.index1].keypoints,
+                            &config.camera2,
+                            &gestures2[*g].keypoints,
+                            &config.triangulation,
+                            config.pointing.min_confidence,
+                        );
+                        pointing_ray(&arm, m.triangulation.point, config.pointing.ray)
+                    }),
+            })
+            .collect();
 
-            headposes = process_map.hpe().unwrap().recv().unwrap();
-            sort_align(&mut headposes, theta);
+        let now = Instant::now();
+        let dt = now.duration_since(last_frame).as_secs_f32();
+        let changes = tracker.update(dt, observations);
+        last_frame = now;
 
-            // Now get the device in line of sight of each head
-            let devices = headposes.iter().zip(positions).map(|(pose, position)| {
-                let (position, gesture) = if let Some((position, gesture)) = position {
-                    (position, gesture)
-                } else {
-                    return None;
-                };
+        for id in changes.born {
+            println!("person {id} appeared");
+        }
+        for id in changes.died {
+            println!("person {id} left");
+            dwell.forget(id);
+            gesture_events.forget(id);
+        }
 
-                let line_of_sight = get_los(&config.camera1, &position, &pose.quat());
+        // Now get the device in line of sight of each person
+        for track in tracker.tracks() {
+            let gaze = track
+                .head_orientation
+                .map(|orientation| gaze_line(&track.smoothed_pos(), &orientation));
+            let line = match config.pointing.cue {
+                TargetCue::Gaze => gaze,
+                TargetCue::Pointing => track.pointing,
+                TargetCue::PointingOrGaze | TargetCue::Fused => track.pointing.or(gaze),
+            };
 
-                get_closest_device_in_los(&config, line_of_sight).map(|x| (x, gesture))
-            });
+            let target = if !track.is_seen() {
+                None
+            } else if config.pointing.cue == TargetCue::Fused {
+                let fusion = &config.fusion;
+                let cues: Vec<Cue> = gaze
+                    .map(|line| Cue {
+                        line,
+                        uncertainty: fusion.gaze_uncertainty,
+                    })
+                    .into_iter()
+                    .chain(track.pointing.map(|line| Cue {
+                        line,
+                        uncertainty: pointing_uncertainty(&line, fusion.keypoint_error),
+                    }))
+                    .collect();
+                fuse_cues(&config, &cues)
+                    .first()
+                    .map(|best| (best.device, format!("fused score {:.2}", best.score)))
+            } else {
+                line.and_then(|line| match config.targeting.mode {
+                    TargetingMode::Ray => get_closest_device_in_los(&config, line)
+                        .map(|hit| (hit.device, format!("{:.2} away", hit.distance))),
+                    TargetingMode::Cone => devices_in_cone(&config, &line)
+                        .first()
+                        .map(|best| (best.device, format!("score {:.2}", best.score))),
+                    TargetingMode::Probabilistic => {
+                        let cov = track.position_covariance.unwrap_or(Mat3A::ZERO);
+                        target_probabilities(&config, &line, cov)
+                            .into_iter()
+                            .next()
+                            .filter(|p| p.probability >= config.uncertainty.min_confidence)
+                            .map(|p| (p.device, format!("p = {:.2}", p.probability)))
+                    }
+                })
+            };
 
-            devices.for_each(|x| {
-                if let Some((device, gesture)) = x {
-                    println!("gesture {:?} on device {}", gesture, device.name);
+            match config.selection.mode {
+                SelectionMode::Gesture => {
+                    // act once per gesture, not on every frame it's held
+                    for event in gesture_events.update(track.id, track.gesture.clone(), dt) {
+                        if matches!(event, GestureEvent::Held { .. }) {
+                            continue;
+                        }
+                        match &target {
+                            Some((device, detail)) => println!(
+                                "person {} {event} on device {} ({detail})",
+                                track.id, device.name
+                            ),
+                            None => println!("person {} {event}", track.id),
+                        }
+                    }
+                }
+                SelectionMode::Dwell => {
+                    let name = target.map(|(device, _)| device.name.as_str());
+                    for event in dwell.update(track.id, name, dt) {
+                        println!("person {} {event}", track.id);
+                    }
                 }
-            });
+            }
         }
 
         Ok(())
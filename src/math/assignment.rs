@@ -0,0 +1,148 @@
+/// Optimal one to one assignment between the rows and columns of a cost
+/// matrix, the Hungarian method. Pairs costing more than `max_cost` (or not
+/// finite) are never made, otherwise as many pairs as possible are made at the
+/// lowest total cost. Returns `(row, column)` pairs sorted by row.
+pub fn assign(cost: &[Vec<f32>], max_cost: f32) -> Vec<(usize, usize)> {
+    let rows = cost.len();
+    let cols = cost.first().map_or(0, Vec::len);
+    if rows == 0 || cols == 0 {
+        return vec![];
+    }
+
+    let allowed = |c: f32| c.is_finite() && c <= max_cost;
+
+    // larger than any total of allowed costs, so a forbidden pair is only
+    // taken when there is nothing else left for that row
+    let max_allowed = cost
+        .iter()
+        .flatten()
+        .filter(|c| allowed(**c))
+        .fold(0.0, |acc: f64, c| acc.max(*c as f64));
+    let forbidden = (max_allowed + 1.0) * (rows.max(cols) + 1) as f64;
+
+    let at = |r: usize, c: usize| {
+        let (r, c) = if rows <= cols { (r, c) } else { (c, r) };
+        let cost = cost[r][c];
+        if allowed(cost) {
+            cost as f64
+        } else {
+            forbidden
+        }
+    };
+
+    let (n, m) = (rows.min(cols), rows.max(cols));
+    let assignment = hungarian(n, m, at);
+
+    let mut pairs: Vec<(usize, usize)> = assignment
+        .into_iter()
+        .enumerate()
+        .map(|(i, j)| if rows <= cols { (i, j) } else { (j, i) })
+        .filter(|(r, c)| allowed(cost[*r][*c]))
+        .collect();
+    pairs.sort_unstable();
+
+    pairs
+}
+
+/// Column for each of the `n` rows of an `n` by `m` matrix, `n <= m`.
+fn hungarian(n: usize, m: usize, cost: impl Fn(usize, usize) -> f64) -> Vec<usize> {
+    // potentials and matching are 1 based, index 0 is the virtual start
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut row_of = vec![0; m + 1];
+    let mut way = vec![0; m + 1];
+
+    for i in 1..=n {
+        row_of[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+
+        loop {
+            used[j0] = true;
+            let i0 = row_of[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+
+            for j in 0..=m {
+                if used[j] {
+                    u[row_of[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+
+            j0 = j1;
+            if row_of[j0] == 0 {
+                break;
+            }
+        }
+
+        while j0 != 0 {
+            let j1 = way[j0];
+            row_of[j0] = row_of[j1];
+            j0 = j1;
+        }
+    }
+
+    let mut col_of = vec![0; n];
+    for j in 1..=m {
+        if row_of[j] != 0 {
+            col_of[row_of[j] - 1] = j - 1;
+        }
+    }
+
+    col_of
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_optimal_assignment() {
+        // greedy would take (0, 0) first and end up with 1 + 10
+        let cost = vec![vec![1.0, 2.0], vec![2.0, 10.0]];
+        assert_eq!(assign(&cost, f32::INFINITY), vec![(0, 1), (1, 0)]);
+
+        let cost = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(assign(&cost, f32::INFINITY), vec![(0, 1), (1, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn handles_rectangular_and_gated() {
+        let wide = vec![vec![5.0, 1.0, 9.0]];
+        assert_eq!(assign(&wide, f32::INFINITY), vec![(0, 1)]);
+
+        let tall = vec![vec![5.0], vec![1.0], vec![9.0]];
+        assert_eq!(assign(&tall, f32::INFINITY), vec![(1, 0)]);
+
+        // row 1 only fits column 0, so row 0 has to give it up
+        let cost = vec![vec![1.0, 3.0], vec![2.0, f32::INFINITY]];
+        assert_eq!(assign(&cost, 5.0), vec![(0, 1), (1, 0)]);
+        assert_eq!(assign(&cost, 2.5), vec![(0, 0)]);
+
+        assert!(assign(&[], 1.0).is_empty());
+        assert!(assign(&[vec![2.0]], 1.0).is_empty());
+    }
+}
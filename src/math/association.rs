@@ -0,0 +1,233 @@
+use super::{assign, triangulate, Rejection, Triangulation};
+use crate::{
+    config::{CameraProperties, RoomConfig, TriangulationLimits},
+    HasImagePosition, ImageCoords,
+};
+
+/// Detections of the two cameras paired up as the same person.
+#[derive(Debug, Clone, Default)]
+pub struct Association {
+    pub matched: Vec<Match>,
+    /// Indices of camera 1 detections nobody in camera 2 fits.
+    pub unmatched1: Vec<usize>,
+    /// Indices of camera 2 detections nobody in camera 1 fits.
+    pub unmatched2: Vec<usize>,
+    /// For unmatched camera 1 detections, why the pairing with the smallest
+    /// ray gap was rejected.
+    pub rejected1: Vec<(usize, Rejection)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub index1: usize,
+    pub index2: usize,
+    pub triangulation: Triangulation,
+}
+
+impl Association {
+    /// The match of a camera 1 detection.
+    pub fn of1(&self, index1: usize) -> Option<&Match> {
+        self.matched.iter().find(|m| m.index1 == index1)
+    }
+}
+
+/// Pairs detections across the cameras by how close their rays come to each
+/// other. Only pairs whose triangulation passes `limits` and lands in `room`
+/// can be matched, and of those the assignment with the lowest total ray gap
+/// is picked.
+pub fn associate<A: HasImagePosition, B: HasImagePosition>(
+    camera1: &CameraProperties,
+    detections1: &[A],
+    camera2: &CameraProperties,
+    detections2: &[B],
+    limits: &TriangulationLimits,
+    room: &RoomConfig,
+) -> Association {
+    let coords2: Vec<ImageCoords> = detections2
+        .iter()
+        .map(|d| d.image_coords(camera2.img_width, camera2.img_height))
+        .collect();
+
+    let mut triangulations: Vec<Vec<Option<Triangulation>>> = detections1
+        .iter()
+        .map(|d1| {
+            let coords1 = d1.image_coords(camera1.img_width, camera1.img_height);
+            coords2
+                .iter()
+                .map(|coords2| {
+                    triangulate(&[(camera1, &coords1), (camera2, coords2)], limits)
+                        .ok()
+                        .map(|t| t.confine(room))
+                })
+                .collect()
+        })
+        .collect();
+
+    let cost: Vec<Vec<f32>> = triangulations
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|t| match t {
+                    Some(t) if t.is_valid() => t.ray_gap,
+                    _ => f32::INFINITY,
+                })
+                .collect()
+        })
+        .collect();
+
+    let pairs = assign(&cost, limits.max_ray_gap);
+
+    let matched: Vec<Match> = pairs
+        .iter()
+        .filter_map(|(i, j)| {
+            Some(Match {
+                index1: *i,
+                index2: *j,
+                triangulation: triangulations[*i][*j].take()?,
+            })
+        })
+        .collect();
+
+    let unmatched1: Vec<usize> = (0..detections1.len())
+        .filter(|i| !matched.iter().any(|m| m.index1 == *i))
+        .collect();
+
+    let rejected1 = unmatched1
+        .iter()
+        .filter_map(|i| {
+            let closest = triangulations[*i]
+                .iter()
+                .flatten()
+                .min_by(|a, b| a.ray_gap.total_cmp(&b.ray_gap))?;
+            Some((*i, closest.rejection?))
+        })
+        .collect();
+
+    Association {
+        unmatched1,
+        unmatched2: (0..detections2.len())
+            .filter(|j| !matched.iter().any(|m| m.index2 == *j))
+            .collect(),
+        rejected1,
+        matched,
+    }
+}
+
+/// Pairs detections of two models run on the same frame by the pixel distance
+/// between them, ignoring pairs further apart than `max_distance`.
+pub fn associate_in_image<A: HasImagePosition, B: HasImagePosition>(
+    detections1: &[A],
+    detections2: &[B],
+    max_distance: f32,
+) -> Vec<(usize, usize)> {
+    let cost: Vec<Vec<f32>> = detections1
+        .iter()
+        .map(|a| {
+            detections2
+                .iter()
+                .map(|b| (a.image_x() - b.image_x()).hypot(a.image_y() - b.image_y()))
+                .collect()
+        })
+        .collect();
+
+    assign(&cost, max_distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3A;
+
+    use super::*;
+    use crate::{math::project_local_dir, HasGlamPosition, HasGlamQuat};
+
+    struct Seen(f32, f32);
+
+    impl HasImagePosition for Seen {
+        fn image_x(&self) -> f32 {
+            self.0
+        }
+
+        fn image_y(&self) -> f32 {
+            self.1
+        }
+    }
+
+    fn see(camera: &CameraProperties, p: Vec3A) -> Seen {
+        let local = camera.quat().inverse().mul_vec3a(p - *camera.pos());
+        let px = project_local_dir(camera, local).unwrap();
+        Seen(px.x, px.y)
+    }
+
+    #[test]
+    fn associates_different_counts_and_orders() {
+        let (camera1, camera2) = CameraProperties::test_stereo_pair();
+
+        let people = [
+            Vec3A::new(4.0, 1.0, 0.2),
+            Vec3A::new(5.0, 2.5, 0.1),
+            Vec3A::new(3.5, 2.0, 0.3),
+        ];
+
+        // camera 2 misses the first person and sees the others in reverse
+        let seen1: Vec<_> = people.iter().map(|p| see(&camera1, *p)).collect();
+        let seen2 = vec![see(&camera2, people[2]), see(&camera2, people[1])];
+
+        let association = associate(
+            &camera1,
+            &seen1,
+            &camera2,
+            &seen2,
+            &TriangulationLimits::default(),
+            &RoomConfig::default(),
+        );
+
+        let pairs: Vec<_> = association
+            .matched
+            .iter()
+            .map(|m| (m.index1, m.index2))
+            .collect();
+        assert_eq!(pairs, vec![(1, 1), (2, 0)]);
+        assert_eq!(association.unmatched1, vec![0]);
+        assert!(association.unmatched2.is_empty());
+        assert!(association
+            .of1(2)
+            .unwrap()
+            .triangulation
+            .point
+            .abs_diff_eq(people[2], 1e-3));
+
+        // with the floor above the first two people only the third fits
+        let room = RoomConfig {
+            floor: Some(0.28),
+            ..Default::default()
+        };
+        let seen2: Vec<_> = people.iter().map(|p| see(&camera2, *p)).collect();
+        let association = associate(
+            &camera1,
+            &seen1,
+            &camera2,
+            &seen2,
+            &TriangulationLimits::default(),
+            &room,
+        );
+        assert_eq!(association.matched.len(), 1);
+        assert_eq!(association.of1(2).unwrap().index2, 2);
+        assert_eq!(association.unmatched1, vec![0, 1]);
+        assert!(association
+            .rejected1
+            .iter()
+            .all(|(_, r)| matches!(r, Rejection::OutsideRoom(_))));
+        assert_eq!(association.rejected1.len(), 2);
+    }
+
+    #[test]
+    fn associates_within_image() {
+        let heads = [Seen(100.0, 100.0), Seen(400.0, 120.0)];
+        let poses = [Seen(390.0, 140.0), Seen(900.0, 100.0), Seen(110.0, 90.0)];
+
+        assert_eq!(
+            associate_in_image(&heads, &poses, 50.0),
+            vec![(0, 2), (1, 0)]
+        );
+    }
+}
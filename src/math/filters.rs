@@ -0,0 +1,154 @@
+use std::f32::consts::TAU;
+
+use glam::{Quat, Vec3A};
+
+use crate::config::OneEuroParams;
+
+/// Weight of the new sample in a low pass with cutoff `cutoff` Hz, for a
+/// sample `dt` seconds after the last.
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+    let tau = 1.0 / (TAU * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+/// One Euro filter: a low pass whose cutoff rises with speed, so it's smooth
+/// when still and keeps up when moving.
+#[derive(Debug, Clone)]
+pub struct OneEuroFilter {
+    params: OneEuroParams,
+    /// Last output and its rate of change.
+    state: Option<(Vec3A, Vec3A)>,
+}
+
+impl OneEuroFilter {
+    pub fn new(params: OneEuroParams) -> Self {
+        Self {
+            params,
+            state: None,
+        }
+    }
+
+    pub fn value(&self) -> Option<Vec3A> {
+        self.state.map(|(x, _)| x)
+    }
+
+    /// Feeds a sample taken `dt` seconds after the last one.
+    pub fn filter(&mut self, x: Vec3A, dt: f32) -> Vec3A {
+        let (prev, prev_dx) = match self.state {
+            None => {
+                self.state = Some((x, Vec3A::ZERO));
+                return x;
+            }
+            Some((prev, _)) if dt <= 0.0 => return prev,
+            Some(state) => state,
+        };
+
+        let dx = prev_dx.lerp((x - prev) / dt, smoothing_factor(self.params.d_cutoff, dt));
+        let cutoff = self.params.min_cutoff + self.params.beta * dx.length();
+        let x = prev.lerp(x, smoothing_factor(cutoff, dt));
+
+        self.state = Some((x, dx));
+        x
+    }
+}
+
+/// One Euro filter for orientations, slerping towards each sample by a factor
+/// that rises with the angular speed.
+#[derive(Debug, Clone)]
+pub struct SlerpFilter {
+    params: OneEuroParams,
+    /// Last output and its angular speed in rad/s.
+    state: Option<(Quat, f32)>,
+}
+
+impl SlerpFilter {
+    pub fn new(params: OneEuroParams) -> Self {
+        Self {
+            params,
+            state: None,
+        }
+    }
+
+    pub fn value(&self) -> Option<Quat> {
+        self.state.map(|(q, _)| q)
+    }
+
+    /// Feeds a sample taken `dt` seconds after the last one.
+    pub fn filter(&mut self, q: Quat, dt: f32) -> Quat {
+        let q = q.normalize();
+        let (prev, prev_speed) = match self.state {
+            None => {
+                self.state = Some((q, 0.0));
+                return q;
+            }
+            Some((prev, _)) if dt <= 0.0 => return prev,
+            Some(state) => state,
+        };
+
+        let speed = prev_speed
+            + (prev.angle_between(q) / dt - prev_speed)
+                * smoothing_factor(self.params.d_cutoff, dt);
+        let cutoff = self.params.min_cutoff + self.params.beta * speed;
+        let q = prev.slerp(q, smoothing_factor(cutoff, dt)).normalize();
+
+        self.state = Some((q, speed));
+        q
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 30.0;
+
+    /// Deterministic jitter in [-1, 1].
+    fn noise(i: usize) -> f32 {
+        ((i * 7919 % 200) as f32 / 100.0) - 1.0
+    }
+
+    #[test]
+    fn one_euro_smooths_jitter_and_follows_motion() {
+        let mut filter = OneEuroFilter::new(OneEuroParams::default());
+
+        let mut spread = 0.0f32;
+        for i in 0..90 {
+            let out = filter.filter(Vec3A::new(noise(i) * 0.05, 1.0, 0.0), DT);
+            if i > 30 {
+                spread = spread.max(out.x.abs());
+            }
+        }
+        assert!(spread < 0.02, "{spread}");
+
+        // walk away at 1 unit/s, should keep up within a few centimetres
+        let mut out = Vec3A::ZERO;
+        for i in 0..60 {
+            out = filter.filter(Vec3A::new(i as f32 * DT, 1.0, 0.0), DT);
+        }
+        assert!((out.x - 59.0 * DT).abs() < 0.15, "{out}");
+    }
+
+    #[test]
+    fn slerp_smooths_jitter_and_follows_turns() {
+        let mut filter = SlerpFilter::new(OneEuroParams::default());
+
+        let mut spread = 0.0f32;
+        for i in 0..90 {
+            let out = filter.filter(Quat::from_rotation_z(noise(i) * 0.1), DT);
+            if i > 30 {
+                spread = spread.max(out.angle_between(Quat::IDENTITY));
+            }
+        }
+        assert!(spread < 0.04, "{spread}");
+
+        let target = Quat::from_rotation_z(1.2);
+        let mut out = Quat::IDENTITY;
+        for _ in 0..60 {
+            out = filter.filter(target, DT);
+        }
+        assert!(out.angle_between(target) < 0.05);
+
+        // flipping the sign of the quaternion is the same orientation
+        assert!(filter.filter(-target, DT).angle_between(target) < 0.05);
+    }
+}
@@ -0,0 +1,334 @@
+//! Vectors tagged with the frame they're in, so a camera space direction can't
+//! be used as a world space one by accident:
+//!
+//! ```compile_fail
+//! use gesture_ease::math::frames::{Camera, Point, World};
+//!
+//! let in_camera: Point<Camera> = Point::new(glam::Vec3A::X);
+//! let in_world: Point<World> = in_camera;
+//! ```
+//!
+//! The frames are
+//! - [`World`]: the room, with z up, in the units of the config.
+//! - [`Camera`]: x along the optical axis, y towards the right of the image
+//!   and z towards its top. [`CameraProperties::camera_to_world`] places it in
+//!   the world, rotated by `Quat::from_euler(EulerRot::ZYX, yaw, pitch, roll)`,
+//!   so yaw turns about the world z axis first.
+//! - [`Head`]: x out of the face. The head pose model reports yaw, pitch and
+//!   roll relative to a head looking straight into the camera, which its
+//!   [`AngleConvention`](crate::config::AngleConvention) turns into a rotation
+//!   about the axes of the world rather than of the camera, for
+//!   [`head_to_world`]. By default that's the same ZYX order as the cameras.
+//! - [`Pixel`]: image coordinates, x to the right and y down.
+
+use std::{
+    marker::PhantomData,
+    ops::{Add, Mul, Sub},
+};
+
+use glam::{Quat, Vec2, Vec3A};
+
+use super::{calc_local_dir_vec, project_local_dir, Line, BASE_FORWARD_VECTOR};
+use crate::{config::CameraProperties, HasGlamPosition, HasGlamQuat, ImageCoords};
+
+pub trait Frame: Copy + std::fmt::Debug {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct World;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Head;
+
+impl Frame for World {}
+impl Frame for Camera {}
+impl Frame for Head {}
+
+/// A position in frame `F`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point<F: Frame> {
+    v: Vec3A,
+    frame: PhantomData<F>,
+}
+
+/// A displacement or direction in frame `F`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector<F: Frame> {
+    v: Vec3A,
+    frame: PhantomData<F>,
+}
+
+impl<F: Frame> Point<F> {
+    pub const ORIGIN: Self = Self::new(Vec3A::ZERO);
+
+    pub const fn new(v: Vec3A) -> Self {
+        Self {
+            v,
+            frame: PhantomData,
+        }
+    }
+
+    pub fn vec(self) -> Vec3A {
+        self.v
+    }
+}
+
+impl<F: Frame> Vector<F> {
+    pub const fn new(v: Vec3A) -> Self {
+        Self {
+            v,
+            frame: PhantomData,
+        }
+    }
+
+    pub fn vec(self) -> Vec3A {
+        self.v
+    }
+
+    pub fn normalize(self) -> Self {
+        Self::new(self.v.normalize())
+    }
+
+    pub fn angle_between(self, other: Self) -> f32 {
+        self.v.angle_between(other.v)
+    }
+}
+
+impl<F: Frame> Sub for Point<F> {
+    type Output = Vector<F>;
+
+    fn sub(self, rhs: Self) -> Vector<F> {
+        Vector::new(self.v - rhs.v)
+    }
+}
+
+impl<F: Frame> Add<Vector<F>> for Point<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Vector<F>) -> Self {
+        Self::new(self.v + rhs.v)
+    }
+}
+
+/// A half line in frame `F`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray<F: Frame> {
+    pub origin: Point<F>,
+    pub dir: Vector<F>,
+}
+
+impl<F: Frame> Ray<F> {
+    pub fn at(&self, t: f32) -> Point<F> {
+        self.origin + Vector::new(self.dir.v * t)
+    }
+}
+
+impl From<Ray<World>> for Line {
+    fn from(ray: Ray<World>) -> Self {
+        Line::new(&ray.origin.v, &ray.dir.v)
+    }
+}
+
+/// A position in an image, x to the right and y down from the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pixel(pub Vec2);
+
+impl From<&ImageCoords> for Pixel {
+    fn from(coords: &ImageCoords) -> Self {
+        Self(Vec2::new(coords.x, coords.y))
+    }
+}
+
+/// Rigid transform taking coordinates in frame `A` to frame `B`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform<A: Frame, B: Frame> {
+    /// Orientation of `A`'s axes in `B`.
+    rot: Quat,
+    /// Origin of `A` in `B`.
+    origin: Vec3A,
+    frames: PhantomData<(A, B)>,
+}
+
+impl<A: Frame, B: Frame> Transform<A, B> {
+    pub fn new(rot: Quat, origin: Point<B>) -> Self {
+        Self {
+            rot,
+            origin: origin.v,
+            frames: PhantomData,
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        self.rot
+    }
+
+    pub fn inverse(&self) -> Transform<B, A> {
+        let rot = self.rot.inverse();
+        Transform {
+            rot,
+            origin: -(rot * self.origin),
+            frames: PhantomData,
+        }
+    }
+}
+
+impl<A: Frame, B: Frame> Mul<Point<A>> for Transform<A, B> {
+    type Output = Point<B>;
+
+    fn mul(self, p: Point<A>) -> Point<B> {
+        Point::new(self.rot * p.v + self.origin)
+    }
+}
+
+impl<A: Frame, B: Frame> Mul<Vector<A>> for Transform<A, B> {
+    type Output = Vector<B>;
+
+    fn mul(self, v: Vector<A>) -> Vector<B> {
+        Vector::new(self.rot * v.v)
+    }
+}
+
+impl<A: Frame, B: Frame> Mul<Ray<A>> for Transform<A, B> {
+    type Output = Ray<B>;
+
+    fn mul(self, ray: Ray<A>) -> Ray<B> {
+        Ray {
+            origin: self * ray.origin,
+            dir: self * ray.dir,
+        }
+    }
+}
+
+/// `B → C` after `A → B` is `A → C`.
+impl<A: Frame, B: Frame, C: Frame> Mul<Transform<A, B>> for Transform<B, C> {
+    type Output = Transform<A, C>;
+
+    fn mul(self, inner: Transform<A, B>) -> Transform<A, C> {
+        Transform {
+            rot: self.rot * inner.rot,
+            origin: self.rot * inner.origin + self.origin,
+            frames: PhantomData,
+        }
+    }
+}
+
+impl CameraProperties {
+    pub fn camera_to_world(&self) -> Transform<Camera, World> {
+        Transform::new(self.quat(), Point::new(*self.pos()))
+    }
+
+    pub fn world_to_camera(&self) -> Transform<World, Camera> {
+        self.camera_to_world().inverse()
+    }
+}
+
+/// The ray from the camera through a pixel.
+pub fn pixel_ray(camera: &CameraProperties, pixel: Pixel) -> Ray<Camera> {
+    let coords = ImageCoords::new(pixel.0.x, pixel.0.y, camera.img_width, camera.img_height);
+
+    Ray {
+        origin: Point::ORIGIN,
+        dir: Vector::new(calc_local_dir_vec(camera, &coords)),
+    }
+}
+
+/// Pixel a point lands on, `None` if it's behind the camera.
+pub fn project(camera: &CameraProperties, p: Point<Camera>) -> Option<Pixel> {
+    project_local_dir(camera, p.v).map(Pixel)
+}
+
+/// Places a head at `head` with the pose `pose` estimated by `camera`. An
+/// identity pose looks straight at the camera.
+pub fn head_to_world(
+    camera: &CameraProperties,
+    head: Point<World>,
+    pose: Quat,
+) -> Transform<Head, World> {
+    let to_camera = Point::new(*camera.pos()) - head;
+    let facing =
+        Quat::from_rotation_arc(BASE_FORWARD_VECTOR.into(), to_camera.normalize().v.into());
+
+    Transform::new(pose * facing, head)
+}
+
+/// Where a head is looking.
+pub fn gaze_ray(camera: &CameraProperties, head: Point<World>, pose: Quat) -> Ray<World> {
+    head_to_world(camera, head, pose)
+        * Ray {
+            origin: Point::ORIGIN,
+            dir: Vector::new(BASE_FORWARD_VECTOR),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn camera(pos: Vec3A, yaw: f32, pitch: f32) -> CameraProperties {
+        CameraProperties::test_new().with_pose(pos, yaw, pitch, 0.0)
+    }
+
+    #[test]
+    fn camera_to_world() {
+        // turned to look along +y, pitched down by 90 degrees it looks along -z
+        let turned = camera(Vec3A::new(1.0, 2.0, 3.0), FRAC_PI_2, 0.0);
+        let p = turned.camera_to_world() * Point::<Camera>::new(Vec3A::new(1.0, 1.0, 0.0));
+        assert!(p.vec().abs_diff_eq(Vec3A::new(0.0, 3.0, 3.0), 1e-6));
+
+        let down = camera(Vec3A::ZERO, 0.0, FRAC_PI_2);
+        let forward = down.camera_to_world() * Vector::<Camera>::new(Vec3A::X);
+        assert!(forward.vec().abs_diff_eq(-Vec3A::Z, 1e-6));
+
+        let back = turned.world_to_camera() * p;
+        assert!(back.vec().abs_diff_eq(Vec3A::new(1.0, 1.0, 0.0), 1e-6));
+
+        // world -> camera of one, then camera -> world of another
+        let relative = down.camera_to_world() * turned.world_to_camera();
+        let q = relative * Point::new(Vec3A::new(1.0, 3.0, 3.0));
+        assert!(q.vec().abs_diff_eq(-Vec3A::Z, 1e-6));
+    }
+
+    #[test]
+    fn pixels_to_camera() {
+        let camera = CameraProperties::test_new();
+        let (w, h) = (camera.img_width as f32, camera.img_height as f32);
+        let ray = |x, y| {
+            pixel_ray(&camera, Pixel(Vec2::new(x, y)))
+                .dir
+                .normalize()
+                .vec()
+        };
+
+        assert!(ray(w / 2.0, h / 2.0).abs_diff_eq(Vec3A::X, 1e-6));
+        // right of the centre is +y, above it is +z, at half the field of view
+        let right = ray(w, h / 2.0);
+        assert!((right.y.atan2(right.x) - camera.fov_x / 2.0).abs() < 1e-5);
+        let up = ray(w / 2.0, 0.0);
+        assert!((up.z.atan2(up.x) - camera.fov_y / 2.0).abs() < 1e-5);
+
+        let seen = project(&camera, Point::new(right * 3.0)).unwrap();
+        assert!(seen.0.abs_diff_eq(Vec2::new(w, h / 2.0), 1e-2));
+        assert!(project(&camera, Point::new(-Vec3A::X)).is_none());
+    }
+
+    #[test]
+    fn head_pose_to_world() {
+        let camera = camera(Vec3A::ZERO, 0.0, 0.0);
+        let head = Point::new(Vec3A::new(4.0, 0.0, 0.0));
+
+        let straight = gaze_ray(&camera, head, Quat::IDENTITY);
+        assert_eq!(straight.origin, head);
+        assert!(straight.dir.vec().abs_diff_eq(-Vec3A::X, 1e-6));
+
+        // turning the head a quarter left about the vertical
+        let turned = gaze_ray(&camera, head, Quat::from_rotation_z(FRAC_PI_2));
+        assert!(turned.dir.vec().abs_diff_eq(-Vec3A::Y, 1e-6));
+
+        // in general the pose turns the direction towards the camera
+        let pose = Quat::from_euler(glam::EulerRot::ZYX, 0.3, -0.2, 0.1);
+        let gaze = gaze_ray(&camera, head, pose);
+        assert!(gaze.dir.vec().abs_diff_eq(pose * -Vec3A::X, 1e-5));
+    }
+}
@@ -0,0 +1,137 @@
+use glam::Vec3A;
+
+use super::{
+    targeting::{angle_off, hidden_from},
+    Line,
+};
+use crate::config::{Config, Device};
+
+/// A line of sight with the standard deviation of its direction, in radians.
+#[derive(Debug, Clone, Copy)]
+pub struct Cue {
+    pub line: Line,
+    pub uncertainty: f32,
+}
+
+/// A device scored against all the cues.
+#[derive(Debug, Clone)]
+pub struct FusedScore<'a> {
+    pub device: &'a Device,
+    /// Likelihood of the device given the cues, in (0, 1].
+    pub score: f32,
+    /// Angle between each cue and the edge of the device, in radians, `None`
+    /// for cues it's hidden from.
+    pub angles: Vec<Option<f32>>,
+}
+
+/// Uncertainty of a pointing ray whose direction runs between two keypoints,
+/// each off by `keypoint_error`.
+pub fn pointing_uncertainty(line: &Line, keypoint_error: f32) -> f32 {
+    (2f32.sqrt() * keypoint_error).atan2(line.dir().length())
+}
+
+/// Scores every device by how well it explains all the cues at once: the
+/// product of a gaussian in the angle off each cue. A cue with a small
+/// uncertainty falls off quickly, so when the cues disagree the more reliable
+/// one wins. Devices outside the gate of every cue are dropped, the rest come
+/// out best first.
+pub fn fuse_cues<'a>(config: &'a Config, cues: &[Cue]) -> Vec<FusedScore<'a>> {
+    let gate = config.fusion.gate;
+    let cues: Vec<(Vec3A, Vec3A, f32)> = cues
+        .iter()
+        .filter_map(|cue| {
+            let dir = cue.line.dir().try_normalize()?;
+            Some((cue.line.anchor(), dir, cue.uncertainty.max(f32::EPSILON)))
+        })
+        .collect();
+
+    let mut scores: Vec<FusedScore> = config
+        .devices
+        .iter()
+        .filter_map(|device| {
+            let angles: Vec<Option<f32>> = cues
+                .iter()
+                .map(|(anchor, dir, _)| {
+                    (!hidden_from(config, *anchor, device))
+                        .then(|| angle_off(*anchor, *dir, device).0)
+                })
+                .collect();
+
+            let in_gate = cues
+                .iter()
+                .zip(&angles)
+                .any(|((_, _, sigma), angle)| angle.is_some_and(|a| a <= gate * sigma));
+            if !in_gate {
+                return None;
+            }
+
+            // a cue the device is hidden from counts as missing it by the gate
+            let exponent: f32 = cues
+                .iter()
+                .zip(&angles)
+                .map(|((_, _, sigma), angle)| {
+                    let z = angle.map_or(gate, |a| a / sigma);
+                    z * z / 2.0
+                })
+                .sum();
+
+            Some(FusedScore {
+                device,
+                score: (-exponent).exp(),
+                angles,
+            })
+        })
+        .collect();
+
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trusts_the_surer_cue() {
+        let config = Config::test_new(vec![
+            Device::test_cube("lamp", Vec3A::new(4.0, 0.0, 0.0), 0.2),
+            Device::test_cube("tv", Vec3A::new(4.0, 1.0, 0.0), 0.2),
+            Device::test_cube("fan", Vec3A::new(-4.0, 0.0, 0.0), 0.2),
+        ]);
+        let head = Vec3A::ZERO;
+        let at = |target: Vec3A, uncertainty: f32| Cue {
+            line: Line::new(&head, &target),
+            uncertainty,
+        };
+
+        // gaze on the lamp, a long steady arm pointing at the tv
+        let gaze = at(Vec3A::X, 8f32.to_radians());
+        let arm = Line::new(&Vec3A::ZERO, &Vec3A::new(4.0, 1.0, 0.0).normalize());
+        let pointing = Cue {
+            line: arm,
+            uncertainty: pointing_uncertainty(&arm, 0.02),
+        };
+        assert!(pointing.uncertainty < gaze.uncertainty);
+
+        let fused = fuse_cues(&config, &[gaze, pointing]);
+        let names: Vec<_> = fused.iter().map(|s| s.device.name.as_str()).collect();
+        assert_eq!(names, ["tv", "lamp"]);
+        assert!(fused.iter().all(|s| s.score > 0.0 && s.score <= 1.0));
+
+        // the same pointing from a short, shaky forearm loses to the gaze
+        let forearm = Line::new(&Vec3A::ZERO, &(0.1 * arm.dir()));
+        let shaky = Cue {
+            line: forearm,
+            uncertainty: pointing_uncertainty(&forearm, 0.05),
+        };
+        let fused = fuse_cues(&config, &[gaze, shaky]);
+        assert_eq!(fused[0].device.name, "lamp");
+
+        let names: Vec<_> = fuse_cues(&config, &[gaze])
+            .iter()
+            .map(|s| s.device.name.clone())
+            .collect();
+        assert_eq!(names, ["lamp", "tv"]);
+        assert!(fuse_cues(&config, &[]).is_empty());
+    }
+}
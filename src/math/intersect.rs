@@ -0,0 +1,165 @@
+use glam::Vec3A;
+
+use super::Line;
+use crate::config::Shape;
+
+/// Distances along the ray, in units of its direction, where it enters and
+/// leaves the axis aligned box spanned by `corner1` and `corner2`. The entry is
+/// 0 when the ray starts inside the box. `None` if the ray misses it or the box
+/// is behind the ray.
+pub fn ray_aabb(line: &Line, corner1: Vec3A, corner2: Vec3A) -> Option<(f32, f32)> {
+    let (anchor, dir) = (line.anchor(), line.dir());
+    let (mut enter, mut exit) = (0.0f32, f32::INFINITY);
+
+    // slab test, one axis at a time
+    for axis in 0..3 {
+        let (lo, hi) = (
+            corner1[axis].min(corner2[axis]),
+            corner1[axis].max(corner2[axis]),
+        );
+
+        if dir[axis] == 0.0 {
+            // parallel to the slab, either always inside it or never
+            if anchor[axis] < lo || anchor[axis] > hi {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (lo - anchor[axis]) / dir[axis];
+        let t2 = (hi - anchor[axis]) / dir[axis];
+        enter = enter.max(t1.min(t2));
+        exit = exit.min(t1.max(t2));
+
+        if enter > exit {
+            return None;
+        }
+    }
+
+    Some((enter, exit))
+}
+
+/// Distance along the ray, in units of its direction, where it first hits
+/// `shape`. For a point, where it passes closest to it.
+pub fn ray_shape(line: &Line, shape: &Shape) -> Option<f32> {
+    match shape {
+        Shape::Aabb { min, max } => ray_aabb(line, *min, *max).map(|(enter, _)| enter),
+        Shape::OrientedBox { center, size, .. } => {
+            // in the box's own frame it's axis aligned
+            let inverse = shape.quat()?.inverse();
+            let local = Line::new(
+                &inverse.mul_vec3a(line.anchor() - *center),
+                &inverse.mul_vec3a(line.dir()),
+            );
+            ray_aabb(&local, -*size / 2.0, *size / 2.0).map(|(enter, _)| enter)
+        }
+        Shape::Sphere { center, radius } => ray_sphere(line, *center, *radius),
+        Shape::Point { pos, radius } => {
+            let len2 = line.dir().length_squared();
+            if len2 == 0.0 {
+                return None;
+            }
+            let t = (*pos - line.anchor()).dot(line.dir()) / len2;
+            (t >= 0.0 && line.at(t).distance(*pos) <= *radius).then_some(t)
+        }
+        Shape::Compound { shapes } => shapes
+            .iter()
+            .filter_map(|s| ray_shape(line, s))
+            .min_by(f32::total_cmp),
+    }
+}
+
+/// Distance along the ray where it enters the sphere, 0 from inside it.
+pub fn ray_sphere(line: &Line, center: Vec3A, radius: f32) -> Option<f32> {
+    let (anchor, dir) = (line.anchor(), line.dir());
+    let a = dir.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+
+    let offset = anchor - center;
+    let half_b = offset.dot(dir);
+    let c = offset.length_squared() - radius * radius;
+    let disc = half_b * half_b - a * c;
+    if disc < 0.0 {
+        return None;
+    }
+
+    let (enter, exit) = ((-half_b - disc.sqrt()) / a, (-half_b + disc.sqrt()) / a);
+    (exit >= 0.0).then_some(enter.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slab_test() {
+        let (min, max) = (Vec3A::new(2.0, -1.0, -1.0), Vec3A::new(4.0, 1.0, 1.0));
+
+        let hit = ray_aabb(&Line::new(&Vec3A::ZERO, &Vec3A::X), min, max);
+        assert_eq!(hit, Some((2.0, 4.0)));
+
+        // reversed corners are the same box
+        assert_eq!(ray_aabb(&Line::new(&Vec3A::ZERO, &Vec3A::X), max, min), hit);
+
+        // behind, beside and grazing past
+        assert!(ray_aabb(&Line::new(&Vec3A::ZERO, &-Vec3A::X), min, max).is_none());
+        assert!(ray_aabb(&Line::new(&Vec3A::new(0.0, 2.0, 0.0), &Vec3A::X), min, max).is_none());
+        let diagonal = Vec3A::new(1.0, 1.0, 0.0).normalize();
+        assert!(ray_aabb(&Line::new(&Vec3A::new(0.0, -0.5, 0.0), &diagonal), min, max).is_none());
+
+        // starting inside
+        let inside = ray_aabb(&Line::new(&Vec3A::new(3.0, 0.0, 0.0), &Vec3A::Y), min, max);
+        assert_eq!(inside, Some((0.0, 1.0)));
+    }
+
+    #[test]
+    fn hits_every_shape() {
+        let line = Line::new(&Vec3A::ZERO, &Vec3A::X);
+        let hit = |shape: &Shape| ray_shape(&line, shape);
+
+        // a thin panel turned 45 degrees about z, centred on the ray
+        let panel = Shape::OrientedBox {
+            center: Vec3A::new(5.0, 0.0, 0.0),
+            size: Vec3A::new(2.0, 0.2, 1.0),
+            yaw: std::f32::consts::FRAC_PI_4,
+            pitch: 0.0,
+            roll: 0.0,
+        };
+        let t = hit(&panel).unwrap();
+        assert!((t - (5.0 - 0.1 * 2f32.sqrt())).abs() < 1e-4, "{t}");
+
+        // a line along the panel just off its face misses it, though not its bounds
+        let along = Vec3A::new(1.0, 1.0, 0.0).normalize();
+        let off_face = Vec3A::new(1.0, -1.0, 0.0).normalize() * 0.3;
+        let beside = Line::new(
+            &(Vec3A::new(5.0, 0.0, 0.0) + off_face - 3.0 * along),
+            &along,
+        );
+        let (min, max) = panel.bounds();
+        assert!(ray_aabb(&beside, min, max).is_some());
+        assert!(ray_shape(&beside, &panel).is_none());
+
+        let sphere = Shape::Sphere {
+            center: Vec3A::new(3.0, 0.5, 0.0),
+            radius: 1.0,
+        };
+        assert!((hit(&sphere).unwrap() - (3.0 - 0.75f32.sqrt())).abs() < 1e-5);
+        assert_eq!(hit(&sphere.translated(Vec3A::Z * 2.0)), None);
+        assert_eq!(hit(&sphere.translated(-Vec3A::X * 3.0)), Some(0.0));
+
+        let point = Shape::Point {
+            pos: Vec3A::new(2.0, 0.05, 0.0),
+            radius: 0.1,
+        };
+        assert_eq!(hit(&point), Some(2.0));
+        assert_eq!(hit(&point.translated(Vec3A::Y)), None);
+        assert_eq!(hit(&point.translated(-Vec3A::X * 4.0)), None);
+
+        let compound = Shape::Compound {
+            shapes: vec![sphere.clone(), point],
+        };
+        assert_eq!(hit(&compound), Some(2.0));
+    }
+}
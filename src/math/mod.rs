@@ -0,0 +1,471 @@
+use error_stack::{Result, ResultExt};
+use glam::{EulerRot, Quat, Vec2, Vec3A};
+
+use crate::{
+    config::{CameraProperties, Config, Device, TriangulationLimits},
+    GError, HasGlamPosition, ImageCoords,
+};
+use frames::Point;
+
+mod assignment;
+mod association;
+mod filters;
+pub mod frames;
+mod fusion;
+mod intersect;
+mod orientation;
+mod pointing;
+mod probability;
+mod projection;
+mod targeting;
+mod triangulation;
+
+pub use assignment::assign;
+pub use association::{associate, associate_in_image, Association, Match};
+pub use filters::{OneEuroFilter, SlerpFilter};
+pub use fusion::{fuse_cues, pointing_uncertainty, Cue, FusedScore};
+pub use intersect::{ray_aabb, ray_shape, ray_sphere};
+pub use orientation::{average_quats, frontalness, fuse_head_poses};
+pub use pointing::{pointing_ray, triangulate_arm, triangulate_hand, Arm};
+pub use probability::{target_probabilities, triangulation_covariance, DeviceProbability};
+pub use projection::{device_visibility, project_point, project_shape, ProjectedShape, Visibility};
+pub use targeting::{devices_in_cone, DeviceScore};
+pub use triangulation::{triangulate, Rejection, Triangulation};
+
+pub const BASE_FORWARD_VECTOR: Vec3A = Vec3A::X;
+pub const EPSILON: f32 = 0.000001; // what should this be
+
+#[derive(Debug, Clone, Copy)]
+pub struct Line {
+    anchor: Vec3A,
+    dir: Vec3A,
+}
+
+impl Line {
+    pub fn new(anchor: &Vec3A, dir: &Vec3A) -> Self {
+        Self {
+            anchor: *anchor,
+            dir: *dir,
+        }
+    }
+
+    pub fn anchor(&self) -> Vec3A {
+        self.anchor
+    }
+
+    pub fn dir(&self) -> Vec3A {
+        self.dir
+    }
+
+    /// Point `t` direction vectors along the line.
+    pub fn at(&self, t: f32) -> Vec3A {
+        self.anchor + t * self.dir
+    }
+
+    /// The same line with a unit direction, so distances along it are lengths.
+    pub fn normalized(&self) -> Option<Self> {
+        Some(Self {
+            anchor: self.anchor,
+            dir: self.dir.try_normalize()?,
+        })
+    }
+
+    pub fn closest_point_bw(&self, other: &Line) -> Result<Vec3A, GError> {
+        // TODO: check if lines are parallel
+        if self.dir.cross(other.dir).abs_diff_eq(Vec3A::ZERO, EPSILON) {
+            return Err(GError::MathError)
+                .attach_printable("The direction vectors of the lines are parallel");
+        }
+
+        let n = self.dir.cross(other.dir).normalize();
+
+        let n1 = self.dir.cross(n);
+        let n2 = other.dir.cross(n);
+
+        let c1 = self.anchor + ((other.anchor - self.anchor).dot(n2) / self.dir.dot(n2)) * self.dir;
+        let c2 =
+            other.anchor + ((self.anchor - other.anchor).dot(n1) / other.dir.dot(n1)) * other.dir;
+
+        Ok(c1.midpoint(c2))
+    }
+}
+
+/// Point closest to the rays through both pixels, without any of the
+/// plausibility checks of [`triangulate`] or [`Triangulation::confine`], so it
+/// can land under the floor. Only for things that aren't heads, like tags.
+pub fn calc_position(
+    camera1: &CameraProperties,
+    img_coords1: &ImageCoords,
+    camera2: &CameraProperties,
+    img_coords2: &ImageCoords,
+) -> Result<Vec3A, GError> {
+    triangulate(
+        &[(camera1, img_coords1), (camera2, img_coords2)],
+        &TriangulationLimits::default(),
+    )
+    .map(|t| t.point)
+}
+
+pub fn calc_pos_dir_vec(camera: &CameraProperties, coords: &ImageCoords) -> Vec3A {
+    (camera.camera_to_world() * frames::pixel_ray(camera, coords.into()).dir).vec()
+}
+
+/// Direction of the ray through a pixel, in the camera frame.
+pub fn calc_local_dir_vec(camera: &CameraProperties, coords: &ImageCoords) -> Vec3A {
+    if camera.has_lens_model() {
+        pinhole_dir_vec(camera, coords)
+    } else {
+        fov_dir_vec(camera, coords)
+    }
+}
+
+/// Pixel a camera frame direction lands on, the inverse of [`calc_local_dir_vec`].
+/// Returns `None` for directions that don't point in front of the camera.
+pub(crate) fn project_local_dir(camera: &CameraProperties, dir: Vec3A) -> Option<Vec2> {
+    if dir.x <= EPSILON {
+        return None;
+    }
+
+    if camera.has_lens_model() {
+        let p = Vec2::new(dir.y / dir.x, -dir.z / dir.x);
+        let p = match &camera.distortion {
+            Some(distortion) => distortion.distort(p),
+            None => p,
+        };
+
+        Some(camera.intrinsics_or_fov().denormalise(p))
+    } else {
+        let dir = dir.normalize();
+        let alpha = (dir.y.atan2(dir.x), (-dir.z).asin());
+        let mid = Vec2::new(camera.img_width as f32, camera.img_height as f32) / 2.0;
+
+        Some(Vec2::new(
+            mid.x + mid.x * alpha.0.tan() / (camera.fov_x / 2.0).tan(),
+            mid.y + mid.y * alpha.1.tan() / (camera.fov_y / 2.0).tan(),
+        ))
+    }
+}
+
+/// Back-projects a pixel through the pinhole model, undistorting it first.
+/// The returned ray is in the camera frame.
+fn pinhole_dir_vec(camera: &CameraProperties, coords: &ImageCoords) -> Vec3A {
+    let p = camera.intrinsics_or_fov().normalise(coords.x, coords.y);
+    let p = match &camera.distortion {
+        Some(distortion) => distortion.undistort(p),
+        None => p,
+    };
+
+    // image x grows along +y and image y grows along -z of the camera
+    Vec3A::new(1.0, p.x, -p.y).normalize()
+}
+
+/// Back-projects a pixel assuming a centred principal point and no distortion.
+/// The returned ray is in the camera frame.
+fn fov_dir_vec(camera: &CameraProperties, coords: &ImageCoords) -> Vec3A {
+    let point_from_mid = coords.coords_from_mid();
+    let r_d = (
+        point_from_mid.0 / coords.x_mid(),
+        point_from_mid.1 / coords.y_mid(),
+    );
+
+    let half_fov = (camera.fov_x / 2.0, camera.fov_y / 2.0);
+
+    let alpha = (
+        (half_fov.0.tan() * r_d.0).atan(),
+        (half_fov.1.tan() * r_d.1).atan(),
+    );
+
+    Quat::from_euler(EulerRot::ZYX, alpha.0, alpha.1, 0.0).mul_vec3a(BASE_FORWARD_VECTOR)
+}
+
+pub fn get_los(camera: &CameraProperties, pos: &Vec3A, quat_relative_to_cam: &Quat) -> Line {
+    frames::gaze_ray(camera, Point::new(*pos), *quat_relative_to_cam).into()
+}
+
+/// Where a head at `pos` with world `orientation` is looking.
+pub fn gaze_line(pos: &Vec3A, orientation: &Quat) -> Line {
+    Line::new(pos, &(*orientation * BASE_FORWARD_VECTOR))
+}
+
+/// A device a line of sight runs into.
+#[derive(Debug, Clone)]
+pub struct DeviceHit<'a> {
+    pub device: &'a Device,
+    /// Distance from the start of the line to where it enters the device.
+    pub distance: f32,
+    /// Where the line enters the device.
+    pub point: Vec3A,
+}
+
+/// Distance along the line, in units of its direction, to the first occluder
+/// it runs into.
+pub fn first_occluder_hit(config: &Config, line: &Line) -> Option<f32> {
+    config
+        .occluders
+        .iter()
+        .filter_map(|o| ray_shape(line, &o.shape))
+        .min_by(f32::total_cmp)
+}
+
+/// Every device the line runs into before any occluder, nearest first.
+pub fn devices_in_los<'a>(config: &'a Config, line: &Line) -> Vec<DeviceHit<'a>> {
+    let Some(line) = line.normalized() else {
+        return vec![];
+    };
+    let blocked_at = first_occluder_hit(config, &line).unwrap_or(f32::INFINITY);
+
+    let mut hits: Vec<DeviceHit> = config
+        .devices
+        .iter()
+        .filter_map(|device| {
+            let distance = ray_shape(&line, &device.shape).filter(|d| *d < blocked_at)?;
+
+            Some(DeviceHit {
+                device,
+                distance,
+                point: line.at(distance),
+            })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits
+}
+
+pub fn get_closest_device_in_los(config: &Config, line: Line) -> Option<DeviceHit<'_>> {
+    devices_in_los(config, &line).into_iter().next()
+}
+
+pub fn angle_bw_cameras_from_z_axis(camera1: &CameraProperties, camera2: &CameraProperties) -> f32 {
+    let rvec = *camera1.pos() - *camera2.pos();
+
+    (rvec.z / rvec.length()).acos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Intrinsics, Occluder, Shape};
+
+    // #[test]
+    // fn test_pos_dir_vec() {
+    //     let camera = CameraProperties::test_new();
+    //     let img_size = (640, 640);
+    //
+    //     let res = calc_pos_dir_vec(&camera, (320.0, 320.0), img_size);
+    //     assert_eq!(*camera.direction_vector(), res);
+    //
+    //     let res = calc_pos_dir_vec(&camera, (640.0, 320.0), img_size);
+    //     assert_eq!(Vec3A::new(0.86602485, 0.5000011, 0.0), res);
+    //
+    //     let res = calc_pos_dir_vec(&camera, (320.0, 640.0), img_size);
+    //     assert_eq!(Vec3A::new(0.86602485, 0.0, -0.5000011), res);
+    // }
+
+    #[test]
+    fn test_find_yaw() {
+        let mut camera1 = CameraProperties::test_new();
+        camera1.pos_x = 3.0;
+        camera1.pos_y = 4.0;
+        camera1.pos_z = 5.0;
+
+        let mut camera2 = CameraProperties::test_new();
+        camera2.pos_x = 0.0;
+        camera2.pos_y = 0.0;
+        camera2.pos_z = 0.0;
+
+        assert_eq!(
+            std::f32::consts::FRAC_PI_4,
+            angle_bw_cameras_from_z_axis(&camera1, &camera2)
+        )
+    }
+
+    #[test]
+    fn test_pinhole_matches_fov_at_centre() {
+        let mut camera = CameraProperties::test_new();
+        camera.yaw = 0.4;
+        let centre = ImageCoords::new(640.0, 360.0, 1280, 720);
+
+        let fov = calc_pos_dir_vec(&camera, &centre);
+        camera.intrinsics = Some(camera.intrinsics_or_fov());
+        let pinhole = calc_pos_dir_vec(&camera, &centre);
+
+        assert!(fov.abs_diff_eq(pinhole, 1e-6));
+        assert!(pinhole.abs_diff_eq(*camera.direction_vector(), 1e-6));
+    }
+
+    #[test]
+    fn test_pinhole_and_fov_off_axis() {
+        let mut camera = CameraProperties::test_new();
+        let pinhole = |camera: &CameraProperties, pixel: &ImageCoords| {
+            let mut camera = camera.clone();
+            camera.intrinsics = Some(camera.intrinsics_or_fov());
+            calc_local_dir_vec(&camera, pixel)
+        };
+
+        // along the image axes both models give the same ray
+        for pixel in [
+            ImageCoords::new(1100.0, 360.0, 1280, 720),
+            ImageCoords::new(640.0, 80.0, 1280, 720),
+        ] {
+            let fov = calc_local_dir_vec(&camera, &pixel);
+            assert!(fov.abs_diff_eq(pinhole(&camera, &pixel), 1e-6));
+        }
+
+        // off both axes the fov model turns by yaw then pitch, so its ray
+        // leaves the image plane more steeply by 1 / cos(yaw)
+        camera.fov_x = 90f32.to_radians();
+        let pixel = ImageCoords::new(1280.0, 540.0, 1280, 720);
+        let fov = calc_local_dir_vec(&camera, &pixel);
+        let expected = pinhole(&camera, &pixel);
+        assert!((fov.y / fov.x - expected.y / expected.x).abs() < 1e-5);
+        assert!((fov.z / fov.x - expected.z / expected.x * 2f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_project_local_dir_roundtrip() {
+        let mut camera = CameraProperties::test_new();
+        let pixel = ImageCoords::new(1000.0, 150.0, 1280, 720);
+
+        let dir = calc_local_dir_vec(&camera, &pixel);
+        let back = project_local_dir(&camera, dir).unwrap();
+        assert!(back.abs_diff_eq(Vec2::new(pixel.x, pixel.y), 1e-2));
+
+        camera.distortion = Some(crate::config::Distortion::BrownConrady {
+            k1: -0.2,
+            k2: 0.05,
+            p1: 0.0,
+            p2: 0.0,
+            k3: 0.0,
+        });
+        let dir = calc_local_dir_vec(&camera, &pixel);
+        let back = project_local_dir(&camera, dir).unwrap();
+        assert!(back.abs_diff_eq(Vec2::new(pixel.x, pixel.y), 1e-2));
+
+        assert!(project_local_dir(&camera, -Vec3A::X).is_none());
+    }
+
+    #[test]
+    fn test_pinhole_off_centre() {
+        let mut camera = CameraProperties::test_new();
+        camera.intrinsics = Some(Intrinsics {
+            fx: 1000.0,
+            fy: 1000.0,
+            cx: 600.0,
+            cy: 400.0,
+        });
+
+        let dir = calc_pos_dir_vec(&camera, &ImageCoords::new(1600.0, 400.0, 1280, 720));
+        assert!(dir.abs_diff_eq(Vec3A::new(1.0, 1.0, 0.0).normalize(), 1e-6));
+
+        let dir = calc_pos_dir_vec(&camera, &ImageCoords::new(600.0, 1400.0, 1280, 720));
+        assert!(dir.abs_diff_eq(Vec3A::new(1.0, 0.0, -1.0).normalize(), 1e-6));
+    }
+
+    #[test]
+    fn test_devices_in_los() {
+        let config: Config = toml::from_str(
+            r#"
+            [camera1]
+            fov_x = 1.0
+            fov_y = 1.0
+            pos_x = 0
+            pos_y = 0
+            pos_z = 0
+            pitch = 0
+            yaw = 0
+            roll = 0
+            img_width = 640
+            img_height = 480
+
+            [camera2]
+            fov_x = 1.0
+            fov_y = 1.0
+            pos_x = 0
+            pos_y = 1
+            pos_z = 0
+            pitch = 0
+            yaw = 0
+            roll = 0
+            img_width = 640
+            img_height = 480
+
+            [[devices]]
+            name = "far"
+            min_x = 6
+            min_y = -1
+            min_z = -1
+            max_x = 7
+            max_y = 1
+            max_z = 1
+
+            [[devices]]
+            name = "near"
+            min_x = 3
+            min_y = -0.5
+            min_z = 0.5
+            max_x = 4
+            max_y = 0.5
+            max_z = -0.5
+
+            [[devices]]
+            name = "beside"
+            min_x = 3
+            min_y = 2
+            min_z = -1
+            max_x = 4
+            max_y = 3
+            max_z = 1
+
+            [[devices]]
+            name = "behind"
+            min_x = -4
+            min_y = -1
+            min_z = -1
+            max_x = -3
+            max_y = 1
+            max_z = 1
+            "#,
+        )
+        .unwrap();
+
+        let line = Line::new(&Vec3A::new(0.0, 0.0, 0.2), &Vec3A::new(2.0, 0.0, 0.0));
+        let hits = devices_in_los(&config, &line);
+
+        let names: Vec<_> = hits.iter().map(|h| h.device.name.as_str()).collect();
+        assert_eq!(names, ["near", "far"]);
+        assert_eq!(hits[0].distance, 3.0);
+        assert_eq!(hits[0].point, Vec3A::new(3.0, 0.0, 0.2));
+
+        let closest = get_closest_device_in_los(&config, line).unwrap();
+        assert_eq!(closest.device.name, "near");
+    }
+
+    #[test]
+    fn test_occluders_block_devices() {
+        let mut config = Config::test_new(vec![
+            Device::test_new("tv", Vec3A::new(6.0, -1.0, -1.0), Vec3A::new(7.0, 1.0, 1.0)),
+            Device::test_new(
+                "lamp",
+                Vec3A::new(2.0, 1.0, -0.2),
+                Vec3A::new(2.4, 1.4, 0.2),
+            ),
+        ]);
+        config.occluders.push(Occluder {
+            name: Some("wall".to_owned()),
+            shape: Shape::Aabb {
+                min: Vec3A::new(4.0, -5.0, -5.0),
+                max: Vec3A::new(4.2, 5.0, 5.0),
+            },
+        });
+
+        let line = Line::new(&Vec3A::ZERO, &Vec3A::X);
+        assert_eq!(first_occluder_hit(&config, &line), Some(4.0));
+        assert!(devices_in_los(&config, &line).is_empty());
+
+        // devices in front of the wall are still selectable
+        let line = Line::new(&Vec3A::new(0.0, 1.2, 0.0), &Vec3A::X);
+        let hit = get_closest_device_in_los(&config, line).unwrap();
+        assert_eq!(hit.device.name, "lamp");
+    }
+}
@@ -0,0 +1,104 @@
+use glam::{Quat, Vec3A};
+
+use super::{
+    frames::{head_to_world, Point},
+    BASE_FORWARD_VECTOR,
+};
+use crate::{config::CameraProperties, HasGlamPosition};
+
+/// Weighted mean of orientations that are close to each other, flipping each
+/// to the same hemisphere as the first so `q` and `-q` count the same. `None`
+/// if the weights don't add up to anything.
+pub fn average_quats(quats: &[(Quat, f32)]) -> Option<Quat> {
+    let (first, _) = quats.first()?;
+
+    let sum = quats.iter().fold(glam::Vec4::ZERO, |sum, (q, w)| {
+        let q = if q.dot(*first) < 0.0 { -*q } else { *q };
+        sum + glam::Vec4::from(q) * *w
+    });
+
+    (sum.length() > f32::EPSILON).then(|| Quat::from_vec4(sum).normalize())
+}
+
+/// How squarely a head at `head` with world `orientation` faces `camera`,
+/// the cosine of the angle between where it looks and the camera.
+pub fn frontalness(camera: &CameraProperties, head: Vec3A, orientation: Quat) -> f32 {
+    let to_camera = (*camera.pos() - head).normalize_or_zero();
+    (orientation * BASE_FORWARD_VECTOR).dot(to_camera)
+}
+
+/// World orientation of a head at `head` from the poses estimated by several
+/// cameras. Views that see the face straight on are the most accurate, so
+/// each is weighted by the square of its frontalness, and ones that see the
+/// back of the head are left out unless there's nothing else.
+pub fn fuse_head_poses(views: &[(&CameraProperties, Quat)], head: Vec3A) -> Option<Quat> {
+    let orientations: Vec<(Quat, f32)> = views
+        .iter()
+        .map(|(camera, pose)| {
+            let orientation = head_to_world(camera, Point::new(head), *pose).rotation();
+            let weight = frontalness(camera, head, orientation).max(0.0).powi(2);
+            (orientation, weight)
+        })
+        .collect();
+
+    average_quats(&orientations).or_else(|| {
+        // every camera sees the back of the head, go with the least bad
+        views
+            .iter()
+            .zip(&orientations)
+            .max_by(|((c1, _), (q1, _)), ((c2, _), (q2, _))| {
+                frontalness(c1, head, *q1).total_cmp(&frontalness(c2, head, *q2))
+            })
+            .map(|(_, (q, _))| *q)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn averages_quats() {
+        let a = Quat::from_rotation_z(0.2);
+        let b = Quat::from_rotation_z(0.6);
+
+        let mean = average_quats(&[(a, 1.0), (-b, 1.0)]).unwrap();
+        assert!(mean.abs_diff_eq(Quat::from_rotation_z(0.4), 1e-6));
+
+        let weighted = average_quats(&[(a, 3.0), (b, 1.0)]).unwrap();
+        assert!((weighted.angle_between(a) - 0.1).abs() < 1e-2);
+
+        assert!(average_quats(&[(a, 0.0)]).is_none());
+        assert!(average_quats(&[]).is_none());
+    }
+
+    #[test]
+    fn fuses_poses_by_frontalness() {
+        let camera1 = CameraProperties::test_new().with_pose(Vec3A::ZERO, 0.0, 0.0, 0.0);
+        let camera2 =
+            CameraProperties::test_new().with_pose(Vec3A::new(4.0, -4.0, 0.0), FRAC_PI_2, 0.0, 0.0);
+        let head = Vec3A::new(4.0, 0.0, 0.0);
+
+        // looking towards camera 2, turned 90 degrees from camera 1
+        let truth = Quat::from_rotation_z(-FRAC_PI_2);
+        let pose1 = truth
+            * head_to_world(&camera1, Point::new(head), Quat::IDENTITY)
+                .rotation()
+                .inverse();
+        let pose2 = Quat::IDENTITY;
+
+        // camera 1 only sees a profile and gets the yaw 20 degrees off
+        let off = Quat::from_rotation_z(-20f32.to_radians()) * pose1;
+        let fused = fuse_head_poses(&[(&camera1, off), (&camera2, pose2)], head).unwrap();
+        let error = fused.angle_between(truth);
+        assert!(error > 0.0 && error < 3f32.to_radians(), "{error}");
+        assert!(frontalness(&camera2, head, fused) > 0.99);
+
+        // from behind only, the less hidden view still gives an answer
+        let behind = Quat::from_rotation_z(FRAC_PI_2) * pose1;
+        let fused = fuse_head_poses(&[(&camera1, behind)], head).unwrap();
+        assert!(frontalness(&camera1, head, fused) < 0.0);
+    }
+}
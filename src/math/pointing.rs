@@ -0,0 +1,197 @@
+use glam::Vec3A;
+
+use super::{triangulate, Line, EPSILON};
+use crate::{
+    config::{CameraProperties, PointingRay, TriangulationLimits},
+    models::{ArmKeypoints, Hand, Keypoint, HAND_LANDMARKS},
+    HasImagePosition,
+};
+
+/// Arm keypoints in the world, `None` where either camera missed one or its
+/// triangulation was rejected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Arm {
+    pub shoulder: Option<Vec3A>,
+    pub elbow: Option<Vec3A>,
+    pub wrist: Option<Vec3A>,
+    pub fingertip: Option<Vec3A>,
+}
+
+/// Triangulates the keypoints both cameras see with at least
+/// `min_confidence`.
+pub fn triangulate_arm(
+    camera1: &CameraProperties,
+    arm1: &ArmKeypoints,
+    camera2: &CameraProperties,
+    arm2: &ArmKeypoints,
+    limits: &TriangulationLimits,
+    min_confidence: f32,
+) -> Arm {
+    let point = |k1: Option<Keypoint>, k2: Option<Keypoint>| {
+        triangulate_keypoint((camera1, &k1?), (camera2, &k2?), limits, min_confidence)
+    };
+
+    Arm {
+        shoulder: point(arm1.shoulder, arm2.shoulder),
+        elbow: point(arm1.elbow, arm2.elbow),
+        wrist: point(arm1.wrist, arm2.wrist),
+        fingertip: point(arm1.fingertip, arm2.fingertip),
+    }
+}
+
+/// Triangulates the landmarks of the same hand seen by both cameras, `None`
+/// where either is less confident than `min_confidence`. Nothing is
+/// triangulated if either hand is less confident than that.
+pub fn triangulate_hand(
+    camera1: &CameraProperties,
+    hand1: &Hand,
+    camera2: &CameraProperties,
+    hand2: &Hand,
+    limits: &TriangulationLimits,
+    min_confidence: f32,
+) -> [Option<Vec3A>; HAND_LANDMARKS] {
+    if hand1.conf < min_confidence || hand2.conf < min_confidence {
+        return [None; HAND_LANDMARKS];
+    }
+
+    std::array::from_fn(|i| {
+        triangulate_keypoint(
+            (camera1, &hand1.landmarks[i]),
+            (camera2, &hand2.landmarks[i]),
+            limits,
+            min_confidence,
+        )
+    })
+}
+
+fn triangulate_keypoint(
+    (camera1, k1): (&CameraProperties, &Keypoint),
+    (camera2, k2): (&CameraProperties, &Keypoint),
+    limits: &TriangulationLimits,
+    min_confidence: f32,
+) -> Option<Vec3A> {
+    if k1.conf < min_confidence || k2.conf < min_confidence {
+        return None;
+    }
+
+    let coords1 = k1.image_coords(camera1.img_width, camera1.img_height);
+    let coords2 = k2.image_coords(camera2.img_width, camera2.img_height);
+    triangulate(&[(camera1, &coords1), (camera2, &coords2)], limits)
+        .ok()?
+        .valid_point()
+}
+
+/// The line a person at `head` points along, starting at the hand so the
+/// body doesn't get in the way. The fingertip stands in for the hand when
+/// it's seen, the wrist otherwise. The direction isn't normalised, its length
+/// is the distance between the two points it was taken from.
+pub fn pointing_ray(arm: &Arm, head: Vec3A, ray: PointingRay) -> Option<Line> {
+    let hand = arm.fingertip.or(arm.wrist)?;
+    let from = match ray {
+        PointingRay::EyeToFingertip => head,
+        PointingRay::Arm => arm.shoulder.or(arm.elbow)?,
+    };
+
+    let dir = hand - from;
+    (dir.length() > EPSILON).then(|| Line::new(&hand, &dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, Device},
+        math::{get_closest_device_in_los, project_point},
+        models::{HandLandmark, Handedness},
+    };
+
+    fn keypoint(camera: &CameraProperties, p: Vec3A) -> Keypoint {
+        let px = project_point(camera, p).unwrap();
+        Keypoint {
+            x: px.x,
+            y: px.y,
+            conf: 0.9,
+        }
+    }
+
+    fn keypoints(camera: &CameraProperties, arm: &[Vec3A; 4]) -> ArmKeypoints {
+        let seen = |p: Vec3A| Some(keypoint(camera, p));
+
+        ArmKeypoints {
+            shoulder: seen(arm[0]),
+            elbow: seen(arm[1]),
+            wrist: seen(arm[2]),
+            fingertip: seen(arm[3]),
+        }
+    }
+
+    #[test]
+    fn points_at_devices() {
+        let (camera1, camera2) = CameraProperties::test_stereo_pair();
+
+        // a straight arm at shoulder height pointing along +y
+        let head = Vec3A::new(4.0, 1.0, 0.3);
+        let arm = [0.0, 0.3, 0.6, 0.75].map(|t| Vec3A::new(4.0, 1.2 + t, 0.1));
+
+        let mut keypoints2 = keypoints(&camera2, &arm);
+        keypoints2.elbow.as_mut().unwrap().conf = 0.1;
+        let seen = triangulate_arm(
+            &camera1,
+            &keypoints(&camera1, &arm),
+            &camera2,
+            &keypoints2,
+            &TriangulationLimits::default(),
+            0.5,
+        );
+        assert!(seen.shoulder.unwrap().abs_diff_eq(arm[0], 1e-3));
+        assert_eq!(seen.elbow, None);
+        assert!(seen.fingertip.unwrap().abs_diff_eq(arm[3], 1e-3));
+
+        let config = Config::test_new(vec![
+            Device::test_new(
+                "lamp",
+                Vec3A::new(3.8, 5.0, -0.1),
+                Vec3A::new(4.2, 5.4, 0.3),
+            ),
+            Device::test_new("tv", Vec3A::new(3.8, 5.0, -0.8), Vec3A::new(4.2, 5.4, -0.4)),
+        ]);
+
+        let along_arm = pointing_ray(&seen, head, PointingRay::Arm).unwrap();
+        assert!(along_arm.anchor().abs_diff_eq(arm[3], 1e-3));
+        let hit = get_closest_device_in_los(&config, along_arm).unwrap();
+        assert_eq!(hit.device.name, "lamp");
+
+        // sighting down the hand from the eyes aims lower, at the tv
+        let sighted = pointing_ray(&seen, head, PointingRay::EyeToFingertip).unwrap();
+        let hit = get_closest_device_in_los(&config, sighted).unwrap();
+        assert_eq!(hit.device.name, "tv");
+
+        assert!(pointing_ray(&Arm::default(), head, PointingRay::EyeToFingertip).is_none());
+    }
+
+    #[test]
+    fn triangulates_hands() {
+        let (camera1, camera2) = CameraProperties::test_stereo_pair();
+        let landmarks: [Vec3A; HAND_LANDMARKS] =
+            std::array::from_fn(|i| Vec3A::new(4.0, 1.5 + 0.01 * i as f32, 0.1));
+        let hand = |camera: &CameraProperties| Hand {
+            handedness: Handedness::Right,
+            conf: 0.9,
+            bbox: Default::default(),
+            landmarks: landmarks.map(|p| keypoint(camera, p)),
+        };
+
+        let mut hand2 = hand(&camera2);
+        hand2.landmarks[HandLandmark::ThumbTip as usize].conf = 0.1;
+        let limits = TriangulationLimits::default();
+        let seen = triangulate_hand(&camera1, &hand(&camera1), &camera2, &hand2, &limits, 0.5);
+        assert!(seen[HandLandmark::IndexTip as usize]
+            .unwrap()
+            .abs_diff_eq(landmarks[HandLandmark::IndexTip as usize], 1e-3));
+        assert_eq!(seen[HandLandmark::ThumbTip as usize], None);
+
+        hand2.conf = 0.2;
+        let unsure = triangulate_hand(&camera1, &hand(&camera1), &camera2, &hand2, &limits, 0.5);
+        assert!(unsure.iter().all(Option::is_none));
+    }
+}
@@ -0,0 +1,227 @@
+use std::f32::consts::TAU;
+
+use glam::{Mat3A, Quat, Vec3A};
+
+use super::{devices_in_los, triangulate, triangulation::outer, Line};
+use crate::{
+    config::{CameraProperties, Config, Device, TriangulationLimits},
+    ImageCoords,
+};
+
+/// Pixel step of the finite differences through the triangulation.
+const PIXEL_STEP: f32 = 0.5;
+
+/// A device and the chance that it's the one a line of sight is on.
+#[derive(Debug, Clone)]
+pub struct DeviceProbability<'a> {
+    pub device: &'a Device,
+    pub probability: f32,
+}
+
+/// Covariance of the point triangulated from `views` when each pixel
+/// coordinate has independent noise of standard deviation `pixel_noise`, to
+/// first order. `None` if the views don't triangulate.
+pub fn triangulation_covariance(
+    views: &[(&CameraProperties, &ImageCoords)],
+    pixel_noise: f32,
+) -> Option<Mat3A> {
+    let limits = TriangulationLimits::default();
+    let point = |views: &[(&CameraProperties, &ImageCoords)]| {
+        triangulate(views, &limits).ok().map(|t| t.point)
+    };
+    let center = point(views)?;
+
+    let mut cov = Mat3A::ZERO;
+    for (i, (camera, coords)) in views.iter().enumerate() {
+        for (dx, dy) in [(PIXEL_STEP, 0.0), (0.0, PIXEL_STEP)] {
+            let moved = ImageCoords::new(
+                coords.x + dx,
+                coords.y + dy,
+                camera.img_width,
+                camera.img_height,
+            );
+            let mut nudged = views.to_vec();
+            nudged[i].1 = &moved;
+
+            let column = (point(&nudged)? - center) / PIXEL_STEP;
+            cov += outer(column, column);
+        }
+    }
+
+    Some(cov * pixel_noise.powi(2))
+}
+
+/// Chance of each device being the first one `line` runs into, when its start
+/// is off with covariance `anchor_cov` and its direction by the configured
+/// angle noise. Estimated from a fixed set of samples, best first; what's
+/// left of 1 is the chance of hitting nothing.
+pub fn target_probabilities<'a>(
+    config: &'a Config,
+    line: &Line,
+    anchor_cov: Mat3A,
+) -> Vec<DeviceProbability<'a>> {
+    let uncertainty = &config.uncertainty;
+    let Some(dir) = line.dir().try_normalize() else {
+        return vec![];
+    };
+    let (across1, across2) = dir.any_orthonormal_pair();
+    let spread = cholesky(anchor_cov);
+
+    let mut rng = SplitMix64(0x5eed);
+    let mut hits = vec![0usize; config.devices.len()];
+    for _ in 0..uncertainty.samples {
+        let offset = spread * Vec3A::new(rng.normal(), rng.normal(), rng.normal());
+        let turn = Quat::from_axis_angle(across1.into(), rng.normal() * uncertainty.angle_noise)
+            * Quat::from_axis_angle(across2.into(), rng.normal() * uncertainty.angle_noise);
+        let sample = Line::new(&(line.anchor() + offset), &(turn * dir));
+
+        if let Some(hit) = devices_in_los(config, &sample).first() {
+            // devices are told apart by address, names needn't be unique
+            if let Some(i) = config
+                .devices
+                .iter()
+                .position(|d| std::ptr::eq(d, hit.device))
+            {
+                hits[i] += 1;
+            }
+        }
+    }
+
+    let mut probabilities: Vec<DeviceProbability> = config
+        .devices
+        .iter()
+        .zip(hits)
+        .filter(|(_, n)| *n > 0)
+        .map(|(device, n)| DeviceProbability {
+            device,
+            probability: n as f32 / uncertainty.samples as f32,
+        })
+        .collect();
+
+    probabilities.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    probabilities
+}
+
+/// Lower triangular `L` with `L Lᵀ = m` for a positive semidefinite `m`,
+/// treating directions without variance as exact.
+fn cholesky(m: Mat3A) -> Mat3A {
+    let a = |r: usize, c: usize| m.col(c)[r];
+    let mut l = [[0.0f32; 3]; 3];
+
+    for r in 0..3 {
+        for c in 0..=r {
+            let sum: f32 = (0..c).map(|k| l[r][k] * l[c][k]).sum();
+            l[r][c] = if r == c {
+                (a(r, r) - sum).max(0.0).sqrt()
+            } else if l[c][c] > 0.0 {
+                (a(r, c) - sum) / l[c][c]
+            } else {
+                0.0
+            };
+        }
+    }
+
+    Mat3A::from_cols(
+        Vec3A::new(l[0][0], l[1][0], l[2][0]),
+        Vec3A::new(l[0][1], l[1][1], l[2][1]),
+        Vec3A::new(l[0][2], l[1][2], l[2][2]),
+    )
+}
+
+/// Small seeded generator, so the samples don't need a dependency and are
+/// the same on every run.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in (0, 1].
+    fn uniform(&mut self) -> f32 {
+        ((self.next_u64() >> 40) + 1) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal, by Box-Muller.
+    fn normal(&mut self) -> f32 {
+        (-2.0 * self.uniform().ln()).sqrt() * (TAU * self.uniform()).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::project_point;
+
+    #[test]
+    fn cholesky_factors() {
+        let m = Mat3A::from_cols(
+            Vec3A::new(4.0, 2.0, 0.4),
+            Vec3A::new(2.0, 5.0, 1.0),
+            Vec3A::new(0.4, 1.0, 3.0),
+        );
+        let l = cholesky(m);
+        assert!((l * l.transpose()).abs_diff_eq(m, 1e-5));
+        assert_eq!(cholesky(Mat3A::ZERO), Mat3A::ZERO);
+    }
+
+    #[test]
+    fn covariance_grows_with_distance() {
+        let (camera1, camera2) = CameraProperties::test_stereo_pair();
+        let cov_at = |p: Vec3A| {
+            let seen = |c: &CameraProperties| {
+                let px = project_point(c, p).unwrap();
+                ImageCoords::new(px.x, px.y, c.img_width, c.img_height)
+            };
+            let (p1, p2) = (seen(&camera1), seen(&camera2));
+            triangulation_covariance(&[(&camera1, &p1), (&camera2, &p2)], 2.0).unwrap()
+        };
+
+        let near = cov_at(Vec3A::new(3.0, 2.0, 0.0));
+        let far = cov_at(Vec3A::new(8.0, 2.0, 0.0));
+        let trace = |m: Mat3A| m.x_axis.x + m.y_axis.y + m.z_axis.z;
+        assert!(trace(near) > 0.0);
+        assert!(trace(far) > 4.0 * trace(near));
+        // symmetric
+        assert!(near.abs_diff_eq(near.transpose(), 1e-6));
+    }
+
+    #[test]
+    fn spreads_probability_over_nearby_devices() {
+        let mut config = Config::test_new(vec![
+            Device::test_cube("lamp", Vec3A::new(4.0, 0.0, 0.0), 0.6),
+            Device::test_cube("tv", Vec3A::new(4.0, 0.5, 0.0), 0.3),
+            Device::test_cube("fan", Vec3A::new(-4.0, 0.0, 0.0), 0.6),
+        ]);
+        let line = Line::new(&Vec3A::ZERO, &Vec3A::X);
+
+        config.uncertainty.angle_noise = 0.0;
+        let exact = target_probabilities(&config, &line, Mat3A::ZERO);
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].device.name, "lamp");
+        assert_eq!(exact[0].probability, 1.0);
+
+        config.uncertainty.angle_noise = 3f32.to_radians();
+        let noisy = target_probabilities(
+            &config,
+            &line,
+            Mat3A::from_diagonal(glam::Vec3::splat(0.01)),
+        );
+        let names: Vec<_> = noisy.iter().map(|p| p.device.name.as_str()).collect();
+        assert_eq!(names, ["lamp", "tv"]);
+        assert!(noisy[0].probability < 1.0 && noisy[0].probability > 0.5);
+        assert!(noisy.iter().map(|p| p.probability).sum::<f32>() <= 1.0);
+
+        // the same inputs give the same answer
+        let again = target_probabilities(
+            &config,
+            &line,
+            Mat3A::from_diagonal(glam::Vec3::splat(0.01)),
+        );
+        assert_eq!(noisy[0].probability, again[0].probability);
+    }
+}
@@ -0,0 +1,144 @@
+use glam::{Vec2, Vec3A};
+
+use super::frames::{self, Point};
+use crate::config::{CameraProperties, Device, Shape};
+
+/// Pieces each wireframe edge is cut into, so edges running behind the
+/// camera or curving under distortion are still drawn where they're visible.
+const EDGE_STEPS: usize = 8;
+
+/// Pixel a world point lands on, `None` if it's behind the camera. The pixel
+/// can be outside the image.
+pub fn project_point(camera: &CameraProperties, p: Vec3A) -> Option<Vec2> {
+    frames::project(camera, camera.world_to_camera() * Point::new(p)).map(|px| px.0)
+}
+
+/// How much of a shape a camera sees, ignoring anything in the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Inside,
+    Partial,
+    Outside,
+}
+
+/// A shape as drawn in a camera's image.
+#[derive(Debug, Clone)]
+pub struct ProjectedShape {
+    /// Segments of the wireframe in front of the camera, in pixels. They can
+    /// reach outside the image.
+    pub edges: Vec<(Vec2, Vec2)>,
+    /// Corners of the box around the projected wireframe, `None` if it's all
+    /// behind the camera.
+    pub bounds: Option<(Vec2, Vec2)>,
+    pub visibility: Visibility,
+}
+
+fn in_image(camera: &CameraProperties, p: Vec2) -> bool {
+    p.x >= 0.0 && p.y >= 0.0 && p.x < camera.img_width as f32 && p.y < camera.img_height as f32
+}
+
+/// Projects the wireframe of `shape` into `camera`'s image.
+pub fn project_shape(camera: &CameraProperties, shape: &Shape) -> ProjectedShape {
+    let mut edges = vec![];
+    let (mut inside, mut total) = (0, 0);
+
+    for (a, b) in shape.wireframe() {
+        let pixels: Vec<Option<Vec2>> = (0..=EDGE_STEPS)
+            .map(|i| project_point(camera, a.lerp(b, i as f32 / EDGE_STEPS as f32)))
+            .collect();
+
+        total += pixels.len();
+        inside += pixels
+            .iter()
+            .flatten()
+            .filter(|p| in_image(camera, **p))
+            .count();
+
+        edges.extend(pixels.windows(2).filter_map(|w| Some((w[0]?, w[1]?))));
+    }
+
+    let bounds =
+        edges
+            .iter()
+            .flat_map(|(a, b)| [*a, *b])
+            .fold(None, |bounds: Option<(Vec2, Vec2)>, p| match bounds {
+                Some((min, max)) => Some((min.min(p), max.max(p))),
+                None => Some((p, p)),
+            });
+
+    let visibility = if inside == 0 {
+        Visibility::Outside
+    } else if inside == total {
+        Visibility::Inside
+    } else {
+        Visibility::Partial
+    };
+
+    ProjectedShape {
+        edges,
+        bounds,
+        visibility,
+    }
+}
+
+/// Whether `device` is in `camera`'s field of view.
+pub fn device_visibility(camera: &CameraProperties, device: &Device) -> Visibility {
+    project_shape(camera, &device.shape).visibility
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::calc_pos_dir_vec, HasGlamPosition, ImageCoords};
+
+    #[test]
+    fn projects_back_onto_pixels() {
+        let camera =
+            CameraProperties::test_new().with_pose(Vec3A::new(1.0, -2.0, 1.5), 0.4, 0.2, 0.0);
+
+        let coords = |x, y| ImageCoords::new(x, y, camera.img_width, camera.img_height);
+
+        for pixel in [Vec2::new(640.0, 360.0), Vec2::new(100.0, 650.0)] {
+            let dir = calc_pos_dir_vec(&camera, &coords(pixel.x, pixel.y));
+            let p = project_point(&camera, *camera.pos() + 3.0 * dir).unwrap();
+            assert!(p.abs_diff_eq(pixel, 1e-2), "{p} != {pixel}");
+        }
+
+        let behind = *camera.pos() - calc_pos_dir_vec(&camera, &coords(0.0, 0.0));
+        assert!(project_point(&camera, behind).is_none());
+    }
+
+    #[test]
+    fn classifies_devices() {
+        let camera = CameraProperties::test_new();
+        let cube = |name, x: f32, y: f32| {
+            Device::test_new(
+                name,
+                Vec3A::new(x - 0.5, y - 0.5, -0.5),
+                Vec3A::new(x + 0.5, y + 0.5, 0.5),
+            )
+        };
+
+        let ahead = project_shape(&camera, &cube("ahead", 5.0, 0.0).shape);
+        assert_eq!(ahead.visibility, Visibility::Inside);
+        let (min, max) = ahead.bounds.unwrap();
+        assert!(min.cmplt(Vec2::new(640.0, 360.0)).all());
+        assert!(max.cmpgt(Vec2::new(640.0, 360.0)).all());
+        assert_eq!(ahead.edges.len(), 12 * EDGE_STEPS);
+
+        // the right edge of the image is at y = x tan(fov_x / 2)
+        let edge = 5.0 * (camera.fov_x / 2.0).tan();
+        assert_eq!(
+            device_visibility(&camera, &cube("edge", 5.0, edge)),
+            Visibility::Partial
+        );
+        assert_eq!(
+            device_visibility(&camera, &cube("aside", 5.0, 3.0 * edge)),
+            Visibility::Outside
+        );
+
+        let behind = project_shape(&camera, &cube("behind", -5.0, 0.0).shape);
+        assert_eq!(behind.visibility, Visibility::Outside);
+        assert!(behind.edges.is_empty() && behind.bounds.is_none());
+    }
+}
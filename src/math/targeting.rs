@@ -0,0 +1,148 @@
+use glam::Vec3A;
+
+use super::{first_occluder_hit, ray_shape, Line};
+use crate::config::{Config, Device};
+
+/// A device near a line of sight and how likely it's the one being aimed at.
+#[derive(Debug, Clone)]
+pub struct DeviceScore<'a> {
+    pub device: &'a Device,
+    /// In (0, 1], higher is better.
+    pub score: f32,
+    /// Angle between the line and the edge of the device, 0 if the line goes
+    /// through it, in radians.
+    pub angle: f32,
+    /// Distance from the start of the line to the centre of the device.
+    pub distance: f32,
+}
+
+/// Scores every device whose bounding sphere is within the configured cone
+/// around the line and isn't hidden behind an occluder, best first. A device
+/// scores higher the closer the line passes to it relative to its apparent
+/// size, and the nearer it is.
+pub fn devices_in_cone<'a>(config: &'a Config, line: &Line) -> Vec<DeviceScore<'a>> {
+    let targeting = &config.targeting;
+    let Some(dir) = line.dir().try_normalize() else {
+        return vec![];
+    };
+
+    let mut scores: Vec<DeviceScore> = config
+        .devices
+        .iter()
+        .filter_map(|device| {
+            let (angle, distance) = angle_off(line.anchor(), dir, device);
+            if angle > targeting.max_cone_angle || hidden_from(config, line.anchor(), device) {
+                return None;
+            }
+
+            // a zero cone only lets through devices the line runs into
+            let alignment = if targeting.max_cone_angle > 0.0 {
+                1.0 - angle / targeting.max_cone_angle
+            } else {
+                1.0
+            };
+            let score = alignment / (1.0 + targeting.distance_falloff * distance);
+
+            Some(DeviceScore {
+                device,
+                score,
+                angle,
+                distance,
+            })
+        })
+        .collect();
+
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+/// Angle between the unit direction `dir` from `from` and the edge of
+/// `device`'s bounding sphere, 0 if it goes through it, and the distance to
+/// the device's centre.
+pub(super) fn angle_off(from: Vec3A, dir: Vec3A, device: &Device) -> (f32, f32) {
+    let (center, radius) = device.shape.bounding_sphere();
+    let to_device = center - from;
+    let distance = to_device.length();
+
+    // starting inside the device's bounding sphere
+    if distance <= radius {
+        return (0.0, distance);
+    }
+
+    let apparent_radius = (radius / distance).asin();
+    (
+        (dir.angle_between(to_device) - apparent_radius).max(0.0),
+        distance,
+    )
+}
+
+/// Whether the sight line from `from` to `device` runs into an occluder first.
+pub(super) fn hidden_from(config: &Config, from: Vec3A, device: &Device) -> bool {
+    let sight = Line::new(&from, &(device.shape.center() - from));
+    let reached_at = ray_shape(&sight, &device.shape).unwrap_or(1.0);
+    first_occluder_hit(config, &sight).is_some_and(|t| t < reached_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Occluder, Shape};
+
+    #[test]
+    fn scores_devices_near_the_gaze() {
+        let config = Config::test_new(vec![
+            // small lamp 5 degrees off the gaze, which a thin ray misses
+            Device::test_cube(
+                "lamp",
+                Vec3A::new(4.0, 4.0 * 5f32.to_radians().tan(), 0.0),
+                0.1,
+            ),
+            // a big tv further out but the same angle off
+            Device::test_cube(
+                "tv",
+                Vec3A::new(8.0, 8.0 * 5f32.to_radians().tan(), 0.0),
+                1.0,
+            ),
+            Device::test_cube("fan", Vec3A::new(4.0, 4.0, 0.0), 0.5),
+            Device::test_cube("behind", Vec3A::new(-4.0, 0.0, 0.0), 0.5),
+        ]);
+
+        let line = Line::new(&Vec3A::ZERO, &Vec3A::X);
+        assert!(crate::math::devices_in_los(&config, &line).is_empty());
+
+        let scores = devices_in_cone(&config, &line);
+        let names: Vec<_> = scores.iter().map(|s| s.device.name.as_str()).collect();
+        assert_eq!(names, ["tv", "lamp"]);
+        assert!(scores.iter().all(|s| s.score > 0.0 && s.score <= 1.0));
+        assert!(scores[0].angle < scores[1].angle);
+
+        let mut config = config;
+        config.occluders.push(Occluder {
+            name: Some("bookshelf".to_owned()),
+            shape: Shape::Aabb {
+                min: Vec3A::new(6.0, 0.5, -1.0),
+                max: Vec3A::new(6.5, 1.5, 1.0),
+            },
+        });
+        let names: Vec<_> = devices_in_cone(&config, &line)
+            .iter()
+            .map(|s| s.device.name.clone())
+            .collect();
+        assert_eq!(names, ["lamp"]);
+
+        config.occluders.clear();
+        config.targeting.max_cone_angle = 2f32.to_radians();
+        let names: Vec<_> = devices_in_cone(&config, &line)
+            .iter()
+            .map(|s| s.device.name.clone())
+            .collect();
+        assert_eq!(names, ["tv"]);
+
+        config.targeting.max_cone_angle = 0.0;
+        let line = Line::new(&Vec3A::ZERO, &Vec3A::new(1.0, 1.0, 0.0));
+        let scores = devices_in_cone(&config, &line);
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].device.name, "fan");
+        assert_eq!(scores[0].score, 1.0 / (1.0 + 0.1 * scores[0].distance));
+    }
+}
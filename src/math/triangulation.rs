@@ -0,0 +1,282 @@
+use std::fmt;
+
+use error_stack::{Result, ResultExt};
+use glam::{Mat3A, Vec3A};
+
+use super::{
+    calc_pos_dir_vec,
+    frames::{project, Pixel, Point, World},
+};
+use crate::{
+    config::{CameraProperties, OutOfRoom, OutOfRoomPolicy, RoomConfig, TriangulationLimits},
+    GError, HasGlamPosition, ImageCoords,
+};
+
+/// Determinant below which the rays are treated as parallel.
+const MIN_NORMAL_DETERMINANT: f32 = 1e-9;
+
+/// A point triangulated from several views, with how well the views agree.
+#[derive(Debug, Clone)]
+pub struct Triangulation {
+    pub point: Vec3A,
+    /// Largest distance between the point and any of the rays.
+    pub ray_gap: f32,
+    /// Smallest angle between any two of the rays, in radians.
+    pub min_ray_angle: f32,
+    /// Distance between the reprojected point and the observation in each
+    /// view, in pixels. `None` where the point is behind the camera.
+    pub reprojection_errors: Vec<Option<f32>>,
+    /// Why the triangulation isn't trustworthy, if it isn't.
+    pub rejection: Option<Rejection>,
+    /// Why the point couldn't be a head in the room, set by
+    /// [`Triangulation::confine`] whether it then rejected or clamped it.
+    pub outside_room: Option<OutOfRoom>,
+}
+
+/// Reason a triangulation was rejected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rejection {
+    BehindCamera { view: usize },
+    RayGap(f32),
+    ReprojectionError { view: usize, pixels: f32 },
+    NarrowAngle(f32),
+    OutsideRoom(OutOfRoom),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BehindCamera { view } => write!(f, "point is behind camera {}", view + 1),
+            Self::RayGap(gap) => write!(f, "rays miss each other by {gap:.3}"),
+            Self::ReprojectionError { view, pixels } => {
+                write!(
+                    f,
+                    "reprojection error of {pixels:.1} px in camera {}",
+                    view + 1
+                )
+            }
+            Self::NarrowAngle(angle) => {
+                write!(f, "rays are only {:.2} deg apart", angle.to_degrees())
+            }
+            Self::OutsideRoom(reason) => write!(f, "point is {reason}"),
+        }
+    }
+}
+
+impl Triangulation {
+    pub fn is_valid(&self) -> bool {
+        self.rejection.is_none()
+    }
+
+    /// The point if it passed all the checks.
+    pub fn valid_point(&self) -> Option<Vec3A> {
+        self.is_valid().then_some(self.point)
+    }
+
+    /// Checks a valid point is somewhere a head could be in `room`, and
+    /// rejects or clamps it if not.
+    pub fn confine(mut self, room: &RoomConfig) -> Self {
+        if !self.is_valid() {
+            return self;
+        }
+
+        if let Some(reason) = room.check_head(self.point) {
+            self.outside_room = Some(reason);
+            match room.out_of_room {
+                OutOfRoomPolicy::Reject => self.rejection = Some(Rejection::OutsideRoom(reason)),
+                OutOfRoomPolicy::Clamp => self.point = room.clamp_head(self.point),
+            }
+        }
+
+        self
+    }
+}
+
+/// Least squares intersection of the rays through `views`, the point with the
+/// smallest summed squared distance to all of them. Fails only when the rays
+/// are parallel, judge the result with [`Triangulation::rejection`].
+pub fn triangulate(
+    views: &[(&CameraProperties, &ImageCoords)],
+    limits: &TriangulationLimits,
+) -> Result<Triangulation, GError> {
+    if views.len() < 2 {
+        return Err(GError::MathError).attach_printable("Triangulation needs at least two views");
+    }
+
+    let rays: Vec<(Vec3A, Vec3A)> = views
+        .iter()
+        .map(|(camera, coords)| (*camera.pos(), calc_pos_dir_vec(camera, coords).normalize()))
+        .collect();
+
+    // sum over rays of (I - d dᵀ) x = (I - d dᵀ) a
+    let (a, b) = rays
+        .iter()
+        .fold((Mat3A::ZERO, Vec3A::ZERO), |(a, b), (anchor, dir)| {
+            let proj = Mat3A::IDENTITY - outer(*dir, *dir);
+            (a + proj, b + proj * *anchor)
+        });
+
+    if a.determinant().abs() < MIN_NORMAL_DETERMINANT {
+        return Err(GError::MathError).attach_printable("The rays are parallel");
+    }
+    let point = a.inverse() * b;
+
+    let ray_gap = rays
+        .iter()
+        .map(|(anchor, dir)| (point - *anchor).reject_from_normalized(*dir).length())
+        .fold(0.0, f32::max);
+
+    let min_ray_angle = rays
+        .iter()
+        .enumerate()
+        .flat_map(|(i, r1)| rays[i + 1..].iter().map(|r2| r1.1.angle_between(r2.1)))
+        .fold(f32::MAX, f32::min);
+
+    let reprojection_errors: Vec<Option<f32>> = views
+        .iter()
+        .map(|(camera, coords)| {
+            let local = camera.world_to_camera() * Point::<World>::new(point);
+            project(camera, local).map(|p| p.0.distance(Pixel::from(*coords).0))
+        })
+        .collect();
+
+    let rejection = if let Some(view) = reprojection_errors.iter().position(Option::is_none) {
+        Some(Rejection::BehindCamera { view })
+    } else if min_ray_angle < limits.min_ray_angle {
+        Some(Rejection::NarrowAngle(min_ray_angle))
+    } else if ray_gap > limits.max_ray_gap {
+        Some(Rejection::RayGap(ray_gap))
+    } else {
+        reprojection_errors
+            .iter()
+            .enumerate()
+            .filter_map(|(view, e)| e.map(|pixels| (view, pixels)))
+            .find(|(_, pixels)| *pixels > limits.max_reprojection_error)
+            .map(|(view, pixels)| Rejection::ReprojectionError { view, pixels })
+    };
+
+    Ok(Triangulation {
+        point,
+        ray_gap,
+        min_ray_angle,
+        reprojection_errors,
+        rejection,
+        outside_room: None,
+    })
+}
+
+pub(super) fn outer(a: Vec3A, b: Vec3A) -> Mat3A {
+    Mat3A::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::project_local_dir, HasGlamQuat};
+
+    fn camera(pos: Vec3A, yaw: f32) -> CameraProperties {
+        CameraProperties::test_new().with_pose(pos, yaw, 0.0, 0.0)
+    }
+
+    fn observe(camera: &CameraProperties, p: Vec3A) -> ImageCoords {
+        let local = camera.quat().inverse().mul_vec3a(p - *camera.pos());
+        let px = project_local_dir(camera, local).unwrap();
+        ImageCoords::new(px.x, px.y, camera.img_width, camera.img_height)
+    }
+
+    #[test]
+    fn three_views_agree() {
+        let target = Vec3A::new(3.0, 1.0, 0.4);
+        let cameras = [
+            camera(Vec3A::ZERO, 0.3),
+            camera(Vec3A::new(0.0, 3.0, 0.0), -0.5),
+            camera(Vec3A::new(6.0, 0.0, 0.5), 2.8),
+        ];
+        let coords: Vec<_> = cameras.iter().map(|c| observe(c, target)).collect();
+        let views: Vec<_> = cameras.iter().zip(&coords).collect();
+
+        let t = triangulate(&views, &TriangulationLimits::default()).unwrap();
+        assert!(t.point.abs_diff_eq(target, 1e-3), "{}", t.point);
+        assert!(t.ray_gap < 1e-3);
+        assert!(t.reprojection_errors.iter().all(|e| e.unwrap() < 0.1));
+        assert!(t.is_valid());
+    }
+
+    #[test]
+    fn matches_two_ray_midpoint() {
+        let c1 = camera(Vec3A::ZERO, 0.3);
+        let c2 = camera(Vec3A::new(0.0, 3.0, 0.0), -0.5);
+        let p1 = ImageCoords::new(700.0, 300.0, 1280, 720);
+        let p2 = ImageCoords::new(500.0, 420.0, 1280, 720);
+
+        let t = triangulate(&[(&c1, &p1), (&c2, &p2)], &TriangulationLimits::default()).unwrap();
+        let l1 = super::super::Line::new(c1.pos(), &calc_pos_dir_vec(&c1, &p1));
+        let l2 = super::super::Line::new(c2.pos(), &calc_pos_dir_vec(&c2, &p2));
+
+        assert!(t.point.abs_diff_eq(l1.closest_point_bw(&l2).unwrap(), 1e-3));
+        // the rays are skew so they can't both go through the point
+        assert!(t.ray_gap > 0.01);
+        assert!(matches!(
+            t.rejection,
+            Some(Rejection::ReprojectionError { view: 0, .. })
+        ));
+
+        let strict = TriangulationLimits {
+            max_ray_gap: 0.1,
+            ..Default::default()
+        };
+        let t = triangulate(&[(&c1, &p1), (&c2, &p2)], &strict).unwrap();
+        assert!(matches!(t.rejection, Some(Rejection::RayGap(_))));
+    }
+
+    #[test]
+    fn rejects_parallel_and_behind() {
+        let c1 = camera(Vec3A::ZERO, 0.0);
+        let c2 = camera(Vec3A::new(0.0, 1.0, 0.0), 0.0);
+        let centre = ImageCoords::new(640.0, 360.0, 1280, 720);
+
+        let parallel = triangulate(&[(&c1, &centre), (&c2, &centre)], &Default::default());
+        assert!(parallel.is_err());
+
+        // two cameras facing away from each other, their rays meet behind both
+        let c2 = camera(Vec3A::new(4.0, 4.0, 0.0), -0.2);
+        let target = Vec3A::new(-2.0, 1.0, 0.0);
+        let dir = |c: &CameraProperties| c.quat().inverse().mul_vec3a(c.pos().to_owned() - target);
+        let p1 = project_local_dir(&c1, dir(&c1)).unwrap();
+        let p2 = project_local_dir(&c2, dir(&c2)).unwrap();
+        let (p1, p2) = (
+            ImageCoords::new(p1.x, p1.y, 1280, 720),
+            ImageCoords::new(p2.x, p2.y, 1280, 720),
+        );
+
+        let t = triangulate(&[(&c1, &p1), (&c2, &p2)], &Default::default()).unwrap();
+        assert!(matches!(t.rejection, Some(Rejection::BehindCamera { .. })));
+        assert!(t.valid_point().is_none());
+    }
+
+    #[test]
+    fn confines_to_the_room() {
+        let c1 = camera(Vec3A::new(0.0, 0.0, 1.0), 0.3);
+        let c2 = camera(Vec3A::new(0.0, 3.0, 1.0), -0.5);
+        let under_floor = Vec3A::new(3.0, 1.0, -0.4);
+        let (p1, p2) = (observe(&c1, under_floor), observe(&c2, under_floor));
+        let t = triangulate(&[(&c1, &p1), (&c2, &p2)], &Default::default()).unwrap();
+        assert!(t.is_valid());
+
+        let mut room = RoomConfig {
+            floor: Some(0.0),
+            ..Default::default()
+        };
+        let rejected = t.clone().confine(&room);
+        assert!(matches!(
+            rejected.rejection,
+            Some(Rejection::OutsideRoom(OutOfRoom::BelowFloor(d))) if (d - 0.4).abs() < 1e-3
+        ));
+
+        room.out_of_room = OutOfRoomPolicy::Clamp;
+        let clamped = t.confine(&room);
+        assert!(clamped.is_valid());
+        assert!(clamped.outside_room.is_some());
+        assert!(clamped.point.abs_diff_eq(Vec3A::new(3.0, 1.0, 0.0), 1e-3));
+    }
+}
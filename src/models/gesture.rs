@@ -0,0 +1,204 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// A gesture recognised in a frame, with whatever the gesture model measured
+/// about it. The gesture worker sends unit variants as plain strings,
+/// `"Fist"`, and the others as objects, `{"Swipe": {"direction": "left",
+/// "velocity": 1.2}}` or `{"Fingers": 3}`.
+#[derive(Default, Debug, Deserialize, Clone, PartialEq)]
+pub enum Gesture {
+    Toggle,
+    Swipe {
+        direction: SwipeDirection,
+        /// Speed of the hand across the image, in image widths per second.
+        velocity: f32,
+    },
+    Pinch {
+        /// Distance between the thumb and index fingertips, relative to the
+        /// size of the hand, so 0 is closed.
+        distance: f32,
+    },
+    OpenPalm,
+    Fist,
+    Point,
+    ThumbsUp,
+    ThumbsDown,
+    /// Number of fingers held up.
+    Fingers(u8),
+    /// A hand pose of the hand pose classifier, by its label.
+    Custom(String),
+    #[default]
+    None,
+}
+
+/// Direction of a swipe as seen by the person making it.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A gesture without its measurements, for matching gestures and naming them
+/// in the config, e.g. `"fist"`, `{ swipe = "left" }`, `{ fingers = 2 }` or
+/// `{ custom = "wave" }`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GestureKind {
+    Toggle,
+    Swipe(SwipeDirection),
+    Pinch,
+    OpenPalm,
+    Fist,
+    Point,
+    ThumbsUp,
+    ThumbsDown,
+    Fingers(u8),
+    Custom(String),
+    None,
+}
+
+impl Gesture {
+    pub fn kind(&self) -> GestureKind {
+        match self {
+            Self::Toggle => GestureKind::Toggle,
+            Self::Swipe { direction, .. } => GestureKind::Swipe(*direction),
+            Self::Pinch { .. } => GestureKind::Pinch,
+            Self::OpenPalm => GestureKind::OpenPalm,
+            Self::Fist => GestureKind::Fist,
+            Self::Point => GestureKind::Point,
+            Self::ThumbsUp => GestureKind::ThumbsUp,
+            Self::ThumbsDown => GestureKind::ThumbsDown,
+            Self::Fingers(n) => GestureKind::Fingers(*n),
+            Self::Custom(label) => GestureKind::Custom(label.clone()),
+            Self::None => GestureKind::None,
+        }
+    }
+
+    /// Whether this is a gesture of `kind`, whatever its measurements.
+    pub fn is(&self, kind: &GestureKind) -> bool {
+        self.kind() == *kind
+    }
+
+    /// Whether this is one of `kinds`.
+    pub fn is_any(&self, kinds: &[GestureKind]) -> bool {
+        kinds.contains(&self.kind())
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+
+    /// Velocity of a swipe, `None` for other gestures.
+    pub fn swipe_velocity(&self) -> Option<f32> {
+        match *self {
+            Self::Swipe { velocity, .. } => Some(velocity),
+            _ => None,
+        }
+    }
+
+    /// Fingertip distance of a pinch, `None` for other gestures.
+    pub fn pinch_distance(&self) -> Option<f32> {
+        match *self {
+            Self::Pinch { distance } => Some(distance),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GestureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toggle => write!(f, "toggle"),
+            Self::Swipe(direction) => write!(f, "swipe {direction:?}"),
+            Self::Pinch => write!(f, "pinch"),
+            Self::OpenPalm => write!(f, "open palm"),
+            Self::Fist => write!(f, "fist"),
+            Self::Point => write!(f, "point"),
+            Self::ThumbsUp => write!(f, "thumbs up"),
+            Self::ThumbsDown => write!(f, "thumbs down"),
+            Self::Fingers(n) => write!(f, "{n} fingers"),
+            Self::Custom(label) => write!(f, "{label}"),
+            Self::None => write!(f, "none"),
+        }
+    }
+}
+
+impl fmt::Display for Gesture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Swipe {
+                direction,
+                velocity,
+            } => write!(f, "swipe {direction:?} at {velocity:.2}"),
+            Self::Pinch { distance } => write!(f, "pinch at {distance:.2}"),
+            _ => self.kind().fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_worker_gestures() {
+        let gestures: Vec<Gesture> = serde_json::from_str(
+            r#"["Toggle", "None", "ThumbsDown", {"Fingers": 3}, {"Custom": "wave"},
+                {"Swipe": {"direction": "left", "velocity": 1.5}},
+                {"Pinch": {"distance": 0.25}}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            gestures,
+            [
+                Gesture::Toggle,
+                Gesture::None,
+                Gesture::ThumbsDown,
+                Gesture::Fingers(3),
+                Gesture::Custom("wave".to_owned()),
+                Gesture::Swipe {
+                    direction: SwipeDirection::Left,
+                    velocity: 1.5
+                },
+                Gesture::Pinch { distance: 0.25 },
+            ]
+        );
+        assert_eq!(gestures[5].swipe_velocity(), Some(1.5));
+        assert_eq!(gestures[6].pinch_distance(), Some(0.25));
+        assert_eq!(gestures[0].pinch_distance(), None);
+    }
+
+    #[test]
+    fn matches_by_kind() {
+        let swipe = Gesture::Swipe {
+            direction: SwipeDirection::Up,
+            velocity: 0.8,
+        };
+        assert!(swipe.is(&GestureKind::Swipe(SwipeDirection::Up)));
+        assert!(!swipe.is(&GestureKind::Swipe(SwipeDirection::Down)));
+        assert!(swipe.is_any(&[GestureKind::Fist, GestureKind::Swipe(SwipeDirection::Up)]));
+        assert!(!Gesture::Fingers(2).is(&GestureKind::Fingers(3)));
+        assert!(Gesture::default().is_none());
+
+        let kinds: Vec<GestureKind> = toml::from_str::<toml::Table>(
+            r#"k = ["fist", { swipe = "left" }, { custom = "wave" }]"#,
+        )
+        .unwrap()["k"]
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            kinds,
+            [
+                GestureKind::Fist,
+                GestureKind::Swipe(SwipeDirection::Left),
+                GestureKind::Custom("wave".to_owned())
+            ]
+        );
+    }
+}
@@ -7,8 +7,9 @@ use std::{
 
 use error_stack::Result;
 use flume::{unbounded, Receiver, Sender};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use super::{Gesture, Hand, Handedness};
 use crate::{
     traits::{Responder, WantIpc},
     GError, HasImagePosition, ImageProcessor,
@@ -109,34 +110,65 @@ impl DerefMut for GesturePreds {
     }
 }
 
-#[derive(Default, Debug, Deserialize, Clone)]
-pub enum Gesture {
-    Toggle,
-    #[default]
-    None,
+#[derive(Default, Debug, Deserialize)]
+pub struct GesturePrediction {
+    pub nose_x: f32,
+    pub nose_y: f32,
+    pub gesture: Gesture,
+    /// Arm keypoints of the gesturing arm, for models that output them.
+    #[serde(default)]
+    pub keypoints: ArmKeypoints,
+    /// Hands with their landmarks, for models that output them.
+    #[serde(default, deserialize_with = "super::hand::deserialize_hands")]
+    pub hands: Vec<Hand>,
 }
 
-impl Gesture {
-    pub fn is_toggle(&self) -> bool {
-        match self {
-            Self::Toggle => true,
-            _ => false,
-        }
+impl GesturePrediction {
+    /// The most confident hand of `handedness`.
+    pub fn hand(&self, handedness: Handedness) -> Option<&Hand> {
+        self.hands
+            .iter()
+            .filter(|h| h.handedness == handedness)
+            .max_by(|a, b| a.conf.total_cmp(&b.conf))
     }
 
-    pub fn is_none(&self) -> bool {
-        match self {
-            Self::None => true,
-            _ => false,
-        }
+    /// The most confident hand.
+    pub fn best_hand(&self) -> Option<&Hand> {
+        self.hands.iter().max_by(|a, b| a.conf.total_cmp(&b.conf))
     }
 }
 
-#[derive(Default, Debug, Deserialize)]
-pub struct GesturePrediction {
-    pub nose_x: f32,
-    pub nose_y: f32,
-    pub gesture: Gesture,
+/// A body or hand landmark in image coordinates.
+#[derive(Default, Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct Keypoint {
+    pub x: f32,
+    pub y: f32,
+    /// Confidence in `0.0..=1.0`, taken as certain when the model leaves it out.
+    #[serde(default = "full_confidence")]
+    pub conf: f32,
+}
+
+fn full_confidence() -> f32 {
+    1.0
+}
+
+impl HasImagePosition for Keypoint {
+    fn image_x(&self) -> f32 {
+        self.x
+    }
+
+    fn image_y(&self) -> f32 {
+        self.y
+    }
+}
+
+#[derive(Default, Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ArmKeypoints {
+    pub shoulder: Option<Keypoint>,
+    pub elbow: Option<Keypoint>,
+    pub wrist: Option<Keypoint>,
+    pub fingertip: Option<Keypoint>,
 }
 
 impl Deref for GesturePrediction {
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::Keypoint;
+use crate::HasImagePosition;
+
+/// Landmarks the gesture model finds on each hand.
+pub const HAND_LANDMARKS: usize = 21;
+
+/// A hand found by the gesture model, e.g.
+///
+/// ```json
+/// {"handedness": "right", "conf": 0.93,
+///  "bbox": {"x1": 410, "y1": 220, "x2": 520, "y2": 350},
+///  "landmarks": [{"x": 455, "y": 340, "conf": 0.9}, ...]}
+/// ```
+///
+/// with the landmarks in the order of [`HandLandmark`].
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(try_from = "HandEntry")]
+pub struct Hand {
+    pub handedness: Handedness,
+    /// Confidence that this is a hand and of its handedness.
+    pub conf: f32,
+    pub bbox: BoundingBox,
+    pub landmarks: [Keypoint; HAND_LANDMARKS],
+}
+
+/// A hand as sent by the worker, with however many landmarks it found.
+#[derive(Deserialize)]
+struct HandEntry {
+    handedness: Handedness,
+    conf: f32,
+    bbox: BoundingBox,
+    landmarks: Vec<Keypoint>,
+}
+
+impl TryFrom<HandEntry> for Hand {
+    type Error = String;
+
+    fn try_from(entry: HandEntry) -> Result<Self, Self::Error> {
+        let count = entry.landmarks.len();
+        let landmarks = entry
+            .landmarks
+            .try_into()
+            .map_err(|_| format!("hand has {count} landmarks, expected {HAND_LANDMARKS}"))?;
+
+        Ok(Self {
+            handedness: entry.handedness,
+            conf: entry.conf,
+            bbox: entry.bbox,
+            landmarks,
+        })
+    }
+}
+
+/// Deserialises a list of hands, dropping the ones without all
+/// [`HAND_LANDMARKS`] landmarks instead of failing the whole prediction.
+pub(crate) fn deserialize_hands<'de, D>(deserializer: D) -> Result<Vec<Hand>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<HandEntry>::deserialize(deserializer)?
+        .into_iter()
+        .filter_map(|entry| Hand::try_from(entry).ok())
+        .collect())
+}
+
+/// Which hand it is, from the person's point of view.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+/// An axis aligned box in image coordinates.
+#[derive(Default, Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+/// The hand landmarks, from the wrist out along each finger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandLandmark {
+    Wrist,
+    ThumbCmc,
+    ThumbMcp,
+    ThumbIp,
+    ThumbTip,
+    IndexMcp,
+    IndexPip,
+    IndexDip,
+    IndexTip,
+    MiddleMcp,
+    MiddlePip,
+    MiddleDip,
+    MiddleTip,
+    RingMcp,
+    RingPip,
+    RingDip,
+    RingTip,
+    PinkyMcp,
+    PinkyPip,
+    PinkyDip,
+    PinkyTip,
+}
+
+impl HandLandmark {
+    pub const FINGERTIPS: [Self; 5] = [
+        Self::ThumbTip,
+        Self::IndexTip,
+        Self::MiddleTip,
+        Self::RingTip,
+        Self::PinkyTip,
+    ];
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f32 {
+        self.x2 - self.x1
+    }
+
+    pub fn height(&self) -> f32 {
+        self.y2 - self.y1
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (self.x1..=self.x2).contains(&x) && (self.y1..=self.y2).contains(&y)
+    }
+}
+
+impl HasImagePosition for BoundingBox {
+    fn image_x(&self) -> f32 {
+        (self.x1 + self.x2) / 2.0
+    }
+
+    fn image_y(&self) -> f32 {
+        (self.y1 + self.y2) / 2.0
+    }
+}
+
+impl Hand {
+    pub fn landmark(&self, landmark: HandLandmark) -> &Keypoint {
+        &self.landmarks[landmark as usize]
+    }
+
+    /// Distance between two landmarks in pixels.
+    pub fn distance(&self, a: HandLandmark, b: HandLandmark) -> f32 {
+        let (a, b) = (self.landmark(a), self.landmark(b));
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    /// Size of the palm in pixels, from the wrist to the base of the middle
+    /// finger, which stays the same however the fingers are held.
+    pub fn palm_size(&self) -> f32 {
+        self.distance(HandLandmark::Wrist, HandLandmark::MiddleMcp)
+    }
+
+    /// Distance between the thumb and index fingertips relative to the palm,
+    /// as in `Gesture::Pinch`. `None` if the palm has no size.
+    pub fn pinch_distance(&self) -> Option<f32> {
+        let palm = self.palm_size();
+        (palm > f32::EPSILON)
+            .then(|| self.distance(HandLandmark::ThumbTip, HandLandmark::IndexTip) / palm)
+    }
+
+    /// Whether the landmarks in `landmarks` are all at least `min_confidence`.
+    pub fn is_confident(&self, landmarks: &[HandLandmark], min_confidence: f32) -> bool {
+        landmarks
+            .iter()
+            .all(|l| self.landmark(*l).conf >= min_confidence)
+    }
+}
+
+impl HasImagePosition for Hand {
+    fn image_x(&self) -> f32 {
+        self.bbox.image_x()
+    }
+
+    fn image_y(&self) -> f32 {
+        self.bbox.image_y()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GesturePrediction;
+
+    #[test]
+    fn parses_hands() {
+        let landmarks: Vec<String> = (0..HAND_LANDMARKS)
+            .map(|i| format!(r#"{{"x": {}, "y": {}}}"#, 100 + i, 200 - i))
+            .collect();
+        let json = format!(
+            r#"{{"nose_x": 10, "nose_y": 20, "gesture": "OpenPalm",
+                "hands": [{{"handedness": "left", "conf": 0.8,
+                            "bbox": {{"x1": 90, "y1": 170, "x2": 130, "y2": 210}},
+                            "landmarks": [{}]}}]}}"#,
+            landmarks.join(", ")
+        );
+        let prediction: GesturePrediction = serde_json::from_str(&json).unwrap();
+
+        let hand = prediction.hand(Handedness::Left).unwrap();
+        assert!(prediction.hand(Handedness::Right).is_none());
+        assert_eq!(hand.landmark(HandLandmark::PinkyTip).x, 120.0);
+        assert_eq!(hand.landmark(HandLandmark::Wrist).conf, 1.0);
+        assert_eq!((hand.image_x(), hand.image_y()), (110.0, 190.0));
+        assert!(hand.bbox.contains(100.0, 200.0));
+
+        // the tips are 4 landmarks apart, the palm 9
+        let diagonal = 2f32.sqrt();
+        assert!((hand.palm_size() - 9.0 * diagonal).abs() < 1e-4);
+        assert!((hand.pinch_distance().unwrap() - 4.0 / 9.0).abs() < 1e-4);
+
+        // a hand with missing landmarks is dropped, not the prediction
+        let json = format!(
+            r#"{{"nose_x": 10, "nose_y": 20, "gesture": "OpenPalm",
+                "hands": [{{"handedness": "left", "conf": 0.8,
+                            "bbox": {{"x1": 90, "y1": 170, "x2": 130, "y2": 210}},
+                            "landmarks": [{}]}},
+                          {{"handedness": "right", "conf": 0.9,
+                            "bbox": {{"x1": 90, "y1": 170, "x2": 130, "y2": 210}},
+                            "landmarks": [{}]}}]}}"#,
+            landmarks.join(", "),
+            landmarks[1..].join(", ")
+        );
+        let prediction: GesturePrediction = serde_json::from_str(&json).unwrap();
+        assert_eq!(prediction.hands.len(), 1);
+        assert_eq!(prediction.hands[0].handedness, Handedness::Left);
+
+        // older workers send no hands
+        let prediction: GesturePrediction =
+            serde_json::from_str(r#"{"nose_x": 1, "nose_y": 2, "gesture": "None"}"#).unwrap();
+        assert!(prediction.hands.is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{Result, ResultExt};
+use serde::{Deserialize, Serialize};
+
+use super::{Hand, HandLandmark, Handedness, HAND_LANDMARKS};
+use crate::{config::HandPoseConfig, GError};
+
+/// A hand pose told apart from the others by its landmarks alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandPose {
+    pub label: String,
+    /// Fraction of the nearest examples with this label.
+    pub agreement: f32,
+    /// Distance to the nearest example with this label, in palm sizes.
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Example {
+    label: String,
+    features: Vec<f32>,
+}
+
+/// Classifies hands by the labels of the `k` nearest recorded examples.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HandPoseClassifier {
+    k: usize,
+    examples: Vec<Example>,
+}
+
+/// The landmarks of `hand` relative to the wrist, turned so the palm points
+/// up the image and in palm sizes, with left hands mirrored so both hands make
+/// the same pose. `None` if the palm has no size.
+pub fn hand_features(hand: &Hand) -> Option<Vec<f32>> {
+    let palm = hand.palm_size();
+    if palm <= f32::EPSILON {
+        return None;
+    }
+
+    let wrist = hand.landmark(HandLandmark::Wrist);
+    let middle = hand.landmark(HandLandmark::MiddleMcp);
+    // unit vector from the wrist along the palm
+    let (ux, uy) = ((middle.x - wrist.x) / palm, (middle.y - wrist.y) / palm);
+    let mirror = match hand.handedness {
+        Handedness::Left => -1.0,
+        Handedness::Right => 1.0,
+    };
+
+    Some(
+        hand.landmarks[1..]
+            .iter()
+            .flat_map(|l| {
+                let (x, y) = ((l.x - wrist.x) / palm, (l.y - wrist.y) / palm);
+                // turns the palm onto -y, image up
+                [mirror * (y * ux - x * uy), -(x * ux + y * uy)]
+            })
+            .collect(),
+    )
+}
+
+impl HandPoseClassifier {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k: k.max(1),
+            examples: vec![],
+        }
+    }
+
+    /// Adds `hand` as an example of `label`, unless it has no palm to
+    /// measure it by.
+    pub fn add(&mut self, label: &str, hand: &Hand) -> bool {
+        let Some(features) = hand_features(hand) else {
+            return false;
+        };
+        self.examples.push(Example {
+            label: label.to_owned(),
+            features,
+        });
+        true
+    }
+
+    /// Trains on a directory with a subdirectory per pose, named after its
+    /// label, of recordings. A recording is a JSON file with a list of hands.
+    pub fn train_dir(dir: &Path, k: usize) -> Result<Self, GError> {
+        let mut classifier = Self::new(k);
+
+        for label_dir in sorted_entries(dir)?.into_iter().filter(|p| p.is_dir()) {
+            let Some(label) = label_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+
+            let recordings = sorted_entries(&label_dir)?
+                .into_iter()
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"));
+            for path in recordings {
+                let hands: Vec<Hand> = serde_json::from_slice(
+                    &fs::read(&path)
+                        .change_context(GError::ClassifierError)
+                        .attach_printable_lazy(|| format!("Couldn't read {}", path.display()))?,
+                )
+                .change_context(GError::ClassifierError)
+                .attach_printable_lazy(|| format!("{} isn't a list of hands", path.display()))?;
+
+                for hand in &hands {
+                    classifier.add(label, hand);
+                }
+            }
+        }
+
+        if classifier.examples.is_empty() {
+            return Err(GError::ClassifierError)
+                .attach_printable(format!("No recorded hands found in {}", dir.display()));
+        }
+        Ok(classifier)
+    }
+
+    pub fn open(path: &Path) -> Result<Self, GError> {
+        serde_json::from_slice(
+            &fs::read(path)
+                .change_context(GError::ClassifierError)
+                .attach_printable_lazy(|| format!("Couldn't read {}", path.display()))?,
+        )
+        .change_context(GError::ClassifierError)
+        .attach_printable("Not a hand pose classifier")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GError> {
+        let json = serde_json::to_vec(self).change_context(GError::ClassifierError)?;
+        fs::write(path, json)
+            .change_context(GError::ClassifierError)
+            .attach_printable_lazy(|| format!("Couldn't write {}", path.display()))
+    }
+
+    /// Number of examples of each label.
+    pub fn labels(&self) -> BTreeMap<&str, usize> {
+        let mut labels = BTreeMap::new();
+        for example in &self.examples {
+            *labels.entry(example.label.as_str()).or_default() += 1;
+        }
+        labels
+    }
+
+    /// The pose most of the nearest examples agree on, if enough of them do
+    /// and it's close enough to `hand`.
+    pub fn classify(&self, hand: &Hand, config: &HandPoseConfig) -> Option<HandPose> {
+        let features = hand_features(hand)?;
+
+        let mut nearest: Vec<(f32, &str)> = self
+            .examples
+            .iter()
+            .map(|e| (distance(&features, &e.features), e.label.as_str()))
+            .collect();
+        nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+        nearest.truncate(self.k);
+
+        // votes and the nearest distance per label, the closest wins a tie
+        let mut votes: BTreeMap<&str, (usize, f32)> = BTreeMap::new();
+        for (d, label) in &nearest {
+            let vote = votes.entry(label).or_insert((0, *d));
+            vote.0 += 1;
+        }
+        let (label, (count, distance)) = votes
+            .into_iter()
+            .max_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)))?;
+
+        let agreement = count as f32 / nearest.len() as f32;
+        (agreement >= config.min_agreement && distance <= config.max_distance).then(|| HandPose {
+            label: label.to_owned(),
+            agreement,
+            distance,
+        })
+    }
+}
+
+/// Root mean square distance between the landmarks of two hands.
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    let squares: f32 = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum();
+    (squares / (HAND_LANDMARKS - 1) as f32).sqrt()
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, GError> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .change_context(GError::ClassifierError)
+        .attach_printable_lazy(|| format!("Couldn't read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BoundingBox, Keypoint};
+
+    /// A hand with its palm 50 px tall and `bend` px between the joints of
+    /// each finger, so a small `bend` is a fist.
+    fn hand(bend: f32, handedness: Handedness) -> Hand {
+        let side = match handedness {
+            Handedness::Left => -1.0,
+            Handedness::Right => 1.0,
+        };
+        let landmarks = std::array::from_fn(|i| {
+            let (x, y) = match i {
+                0 => (0.0, 0.0),
+                _ => {
+                    let (finger, joint) = ((i - 1) / 4, (i - 1) % 4);
+                    let x = side * 10.0 * (finger as f32 - 2.0);
+                    (x, -50.0 - bend * joint as f32)
+                }
+            };
+            Keypoint {
+                x: 300.0 + x,
+                y: 300.0 + y,
+                conf: 1.0,
+            }
+        });
+
+        Hand {
+            handedness,
+            conf: 1.0,
+            bbox: BoundingBox::default(),
+            landmarks,
+        }
+    }
+
+    fn trained() -> HandPoseClassifier {
+        let mut classifier = HandPoseClassifier::new(3);
+        for bend in [18.0, 20.0, 22.0] {
+            assert!(classifier.add("open", &hand(bend, Handedness::Right)));
+        }
+        for bend in [1.0, 2.0, 3.0] {
+            classifier.add("fist", &hand(bend, Handedness::Right));
+        }
+        classifier
+    }
+
+    #[test]
+    fn classifies_by_nearest_examples() {
+        let classifier = trained();
+        let config = HandPoseConfig::default();
+        let label = |hand: &Hand| classifier.classify(hand, &config).map(|p| p.label);
+
+        assert_eq!(
+            label(&hand(19.0, Handedness::Right)).as_deref(),
+            Some("open")
+        );
+        assert_eq!(
+            label(&hand(0.0, Handedness::Right)).as_deref(),
+            Some("fist")
+        );
+        // mirrored, a left hand makes the same pose
+        assert_eq!(
+            label(&hand(21.0, Handedness::Left)).as_deref(),
+            Some("open")
+        );
+
+        let pose = classifier.classify(&hand(20.0, Handedness::Right), &config);
+        assert_eq!(pose.unwrap().agreement, 1.0);
+
+        // fingers bent back are unlike any example
+        assert_eq!(label(&hand(-40.0, Handedness::Right)), None);
+    }
+
+    #[test]
+    fn classifies_tilted_hands() {
+        let classifier = trained();
+        let config = HandPoseConfig::default();
+        let tilt = |mut hand: Hand, angle: f32| {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            for l in &mut hand.landmarks {
+                let (x, y) = (l.x - 300.0, l.y - 300.0);
+                l.x = 300.0 + x * cos - y * sin;
+                l.y = 300.0 + x * sin + y * cos;
+            }
+            hand
+        };
+
+        for angle in [30.0, -45.0, 160.0] {
+            let open = classifier.classify(&tilt(hand(20.0, Handedness::Right), angle), &config);
+            let open = open.unwrap();
+            assert_eq!(open.label, "open");
+            assert!(open.distance < 1e-4, "{angle}: {}", open.distance);
+
+            let fist = classifier.classify(&tilt(hand(2.0, Handedness::Left), angle), &config);
+            assert_eq!(fist.unwrap().label, "fist");
+        }
+    }
+
+    #[test]
+    fn trains_from_recordings() {
+        let dir = std::env::temp_dir().join(format!("hand_poses_{}", std::process::id()));
+        for (label, bends) in [("open", [18.0, 22.0]), ("fist", [1.0, 3.0])] {
+            fs::create_dir_all(dir.join(label)).unwrap();
+            let hands: Vec<Hand> = bends.iter().map(|b| hand(*b, Handedness::Right)).collect();
+            fs::write(
+                dir.join(label).join("take1.json"),
+                serde_json::to_vec(&hands).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let classifier = HandPoseClassifier::train_dir(&dir, 1).unwrap();
+        assert_eq!(
+            classifier.labels(),
+            BTreeMap::from([("fist", 2), ("open", 2)])
+        );
+
+        let path = dir.join("model.json");
+        classifier.save(&path).unwrap();
+        let loaded = HandPoseClassifier::open(&path).unwrap();
+        let pose = loaded
+            .classify(&hand(2.0, Handedness::Right), &HandPoseConfig::default())
+            .unwrap();
+        assert_eq!(pose.label, "fist");
+
+        assert!(HandPoseClassifier::train_dir(&dir.join("open"), 1).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
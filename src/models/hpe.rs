@@ -10,6 +10,7 @@ use flume::{unbounded, Receiver, Sender};
 use serde::Deserialize;
 
 use crate::{
+    config::AngleConvention,
     traits::{Responder, WantIpc},
     GError, HasGlamQuat, HasImagePosition, ImageProcessor,
 };
@@ -21,10 +22,11 @@ pub struct HeadPoseEstimation {
     response_sender: Sender<HPEPreds>,
     response_receiver: Receiver<HPEPreds>,
     unix_stream: Arc<UnixStream>,
+    angles: AngleConvention,
 }
 
 impl HeadPoseEstimation {
-    pub fn new(unix_stream: UnixStream) -> Self {
+    pub fn new(unix_stream: UnixStream, angles: AngleConvention) -> Self {
         let (image_sender, image_receiver) = unbounded();
         let (response_sender, response_receiver) = unbounded();
         let unix_stream = Arc::new(unix_stream);
@@ -35,6 +37,7 @@ impl HeadPoseEstimation {
             response_sender,
             response_receiver,
             unix_stream,
+            angles,
         }
     }
 
@@ -47,7 +50,10 @@ impl HeadPoseEstimation {
 
             instance.send_ipc(&_img, w, h).unwrap();
             let res = instance.recv_ipc().unwrap();
-            let res: HPEPreds = serde_json::from_slice(&res).unwrap();
+            let mut res: HPEPreds = serde_json::from_slice(&res).unwrap();
+            for pred in res.iter_mut() {
+                pred.angles = instance.angles;
+            }
 
             instance.send_response(res).unwrap();
         })
@@ -120,6 +126,9 @@ pub struct HpePrediction {
     pub pitch: f32,
     pub yaw: f32,
     pub roll: f32,
+    /// Set from the model's config when the prediction comes in.
+    #[serde(skip)]
+    pub angles: AngleConvention,
 }
 
 impl HasImagePosition for HpePrediction {
@@ -133,7 +142,10 @@ impl HasImagePosition for HpePrediction {
 }
 
 impl HasGlamQuat for HpePrediction {
+    /// Head rotation relative to looking straight into the camera, read with
+    /// the model's [`AngleConvention`] and about the world axes, see
+    /// [`crate::math::frames::head_to_world`].
     fn quat(&self) -> glam::Quat {
-        glam::Quat::from_euler(glam::EulerRot::ZYX, self.yaw, self.pitch, self.roll)
+        self.angles.quat(self.yaw, self.pitch, self.roll)
     }
 }
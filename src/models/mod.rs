@@ -1,7 +1,15 @@
+mod gesture;
 mod gesture_recognition;
+mod hand;
+mod hand_pose;
 mod head_detection;
 mod hpe;
 
-pub use gesture_recognition::{Gesture, GestureDetection, GesturePrediction, GesturePreds};
+pub use gesture::{Gesture, GestureKind, SwipeDirection};
+pub use gesture_recognition::{
+    ArmKeypoints, GestureDetection, GesturePrediction, GesturePreds, Keypoint,
+};
+pub use hand::{BoundingBox, Hand, HandLandmark, Handedness, HAND_LANDMARKS};
+pub use hand_pose::{hand_features, HandPose, HandPoseClassifier};
 pub use head_detection::{HeadDetection, HeadPrediction, HeadPreds};
 pub use hpe::{HPEPreds, HeadPoseEstimation, HpePrediction};
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{config::SelectionConfig, tracking::TrackId};
+
+/// What happened to a person's focus in an update.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DwellEvent {
+    FocusStarted {
+        device: String,
+    },
+    /// Fraction of the dwell time spent on the device so far, below 1.
+    FocusProgress {
+        device: String,
+        progress: f32,
+    },
+    FocusLost {
+        device: String,
+    },
+    Selected {
+        device: String,
+    },
+}
+
+impl fmt::Display for DwellEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FocusStarted { device } => write!(f, "started looking at {device}"),
+            Self::FocusProgress { device, progress } => {
+                write!(f, "looking at {device}, {:.0}%", progress * 100.0)
+            }
+            Self::FocusLost { device } => write!(f, "looked away from {device}"),
+            Self::Selected { device } => write!(f, "selected {device}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Focus {
+    device: String,
+    /// Seconds spent looking at the device.
+    dwelt: f32,
+    /// Seconds since last looking at it.
+    away: f32,
+    selected: bool,
+}
+
+/// Selects the device a person keeps looking at. Once selected, a device
+/// can only be selected again after the focus on it has been lost.
+#[derive(Debug)]
+pub struct DwellSelector {
+    config: SelectionConfig,
+    focus: HashMap<TrackId, Focus>,
+}
+
+impl DwellSelector {
+    pub fn new(config: SelectionConfig) -> Self {
+        Self {
+            config,
+            focus: HashMap::new(),
+        }
+    }
+
+    /// Device `person` is focused on, whether or not it's been selected.
+    pub fn focus(&self, person: TrackId) -> Option<&str> {
+        self.focus.get(&person).map(|f| f.device.as_str())
+    }
+
+    /// Feeds the device `person` is looking at `dt` seconds after the last
+    /// update, `None` if they aren't looking at any.
+    pub fn update(&mut self, person: TrackId, target: Option<&str>, dt: f32) -> Vec<DwellEvent> {
+        let config = &self.config;
+        let mut events = vec![];
+
+        if let Some(focus) = self.focus.get_mut(&person) {
+            if target == Some(focus.device.as_str()) {
+                focus.away = 0.0;
+                if !focus.selected {
+                    focus.dwelt += dt;
+                    if focus.dwelt >= config.dwell_time {
+                        focus.selected = true;
+                        events.push(DwellEvent::Selected {
+                            device: focus.device.clone(),
+                        });
+                    } else {
+                        events.push(DwellEvent::FocusProgress {
+                            device: focus.device.clone(),
+                            progress: focus.dwelt / config.dwell_time,
+                        });
+                    }
+                }
+                return events;
+            }
+
+            focus.away += dt;
+            if focus.away < config.leave_time {
+                return events;
+            }
+
+            events.push(DwellEvent::FocusLost {
+                device: focus.device.clone(),
+            });
+            self.focus.remove(&person);
+        }
+
+        if let Some(device) = target {
+            self.focus.insert(
+                person,
+                Focus {
+                    device: device.to_owned(),
+                    dwelt: 0.0,
+                    away: 0.0,
+                    selected: false,
+                },
+            );
+            events.push(DwellEvent::FocusStarted {
+                device: device.to_owned(),
+            });
+        }
+
+        events
+    }
+
+    /// Drops the focus of a person who's gone.
+    pub fn forget(&mut self, person: TrackId) {
+        self.focus.remove(&person);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.125;
+
+    fn selector() -> DwellSelector {
+        DwellSelector::new(SelectionConfig {
+            dwell_time: 1.0,
+            leave_time: 0.3,
+            ..Default::default()
+        })
+    }
+
+    fn run(selector: &mut DwellSelector, target: Option<&str>, frames: usize) -> Vec<DwellEvent> {
+        (0..frames)
+            .flat_map(|_| selector.update(TrackId(0), target, DT))
+            .collect()
+    }
+
+    fn selected(events: &[DwellEvent]) -> usize {
+        events
+            .iter()
+            .filter(|e| matches!(e, DwellEvent::Selected { .. }))
+            .count()
+    }
+
+    #[test]
+    fn selects_after_dwelling() {
+        let mut selector = selector();
+
+        let events = run(&mut selector, Some("lamp"), 5);
+        assert_eq!(
+            events[0],
+            DwellEvent::FocusStarted {
+                device: "lamp".to_owned()
+            }
+        );
+        assert!(matches!(
+            events.last(),
+            Some(DwellEvent::FocusProgress { progress, .. }) if *progress == 0.5
+        ));
+
+        // a glance away is forgiven and doesn't count
+        assert!(run(&mut selector, Some("tv"), 2).is_empty());
+
+        let events = run(&mut selector, Some("lamp"), 10);
+        assert_eq!(selected(&events), 1);
+        assert_eq!(
+            events
+                .iter()
+                .position(|e| matches!(e, DwellEvent::Selected { .. })),
+            Some(3)
+        );
+        assert_eq!(selector.focus(TrackId(0)), Some("lamp"));
+    }
+
+    #[test]
+    fn looking_away_resets() {
+        let mut selector = selector();
+
+        run(&mut selector, Some("lamp"), 8);
+        let events = run(&mut selector, Some("tv"), 3);
+        assert_eq!(
+            events,
+            [
+                DwellEvent::FocusLost {
+                    device: "lamp".to_owned()
+                },
+                DwellEvent::FocusStarted {
+                    device: "tv".to_owned()
+                }
+            ]
+        );
+
+        // selected once, then again only after looking away for long enough
+        assert_eq!(selected(&run(&mut selector, Some("tv"), 20)), 1);
+        run(&mut selector, None, 2);
+        assert_eq!(selected(&run(&mut selector, Some("tv"), 20)), 0);
+        assert!(
+            run(&mut selector, None, 3).contains(&DwellEvent::FocusLost {
+                device: "tv".to_owned()
+            })
+        );
+        assert_eq!(selected(&run(&mut selector, Some("tv"), 20)), 1);
+
+        selector.forget(TrackId(0));
+        assert_eq!(selector.focus(TrackId(0)), None);
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt;
+
+use glam::{Mat3A, Quat, Vec3A};
+
+use crate::{
+    config::{SmoothingConfig, TrackingConfig},
+    math::{assign, Line, OneEuroFilter, SlerpFilter},
+    models::Gesture,
+    HasGlamPosition,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackId(pub u32);
+
+impl fmt::Display for TrackId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// One person seen in a frame.
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub position: Vec3A,
+    /// Covariance of `position` from the pixel noise, if known.
+    pub position_covariance: Option<Mat3A>,
+    pub gesture: Gesture,
+    /// Orientation of the head in the world.
+    pub head_orientation: Option<Quat>,
+    pub pointing: Option<Line>,
+}
+
+/// Constant velocity Kalman filter. The axes are independent and share their
+/// noise, so one 2x2 covariance serves all three.
+#[derive(Debug, Clone)]
+struct ConstantVelocity {
+    pos: Vec3A,
+    vel: Vec3A,
+    /// Covariance of (position, velocity) along any axis.
+    cov: [[f32; 2]; 2],
+}
+
+impl ConstantVelocity {
+    fn new(pos: Vec3A, config: &TrackingConfig) -> Self {
+        let r = config.measurement_noise.powi(2);
+        // a new track could be moving at walking pace in any direction
+        Self {
+            pos,
+            vel: Vec3A::ZERO,
+            cov: [[r, 0.0], [0.0, 1.0]],
+        }
+    }
+
+    fn predict(&mut self, dt: f32, config: &TrackingConfig) {
+        self.pos += self.vel * dt;
+
+        let [[pp, pv], [_, vv]] = self.cov;
+        let q = config.acceleration_noise.powi(2);
+        let pp = pp + 2.0 * dt * pv + dt * dt * vv + q * dt.powi(4) / 4.0;
+        let pv = pv + dt * vv + q * dt.powi(3) / 2.0;
+        let vv = vv + q * dt * dt;
+        self.cov = [[pp, pv], [pv, vv]];
+    }
+
+    /// Innovation variance of a position measurement.
+    fn innovation_var(&self, config: &TrackingConfig) -> f32 {
+        self.cov[0][0] + config.measurement_noise.powi(2)
+    }
+
+    fn mahalanobis(&self, z: Vec3A, config: &TrackingConfig) -> f32 {
+        (z - self.pos).length() / self.innovation_var(config).sqrt()
+    }
+
+    fn update(&mut self, z: Vec3A, config: &TrackingConfig) {
+        let s = self.innovation_var(config);
+        let [[pp, pv], [_, vv]] = self.cov;
+        let (kp, kv) = (pp / s, pv / s);
+
+        let y = z - self.pos;
+        self.pos += kp * y;
+        self.vel += kv * y;
+
+        let pv_new = (1.0 - kp) * pv;
+        self.cov = [[(1.0 - kp) * pp, pv_new], [pv_new, vv - kv * pv]];
+    }
+}
+
+/// A person followed across frames.
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub id: TrackId,
+    filter: ConstantVelocity,
+    hits: u32,
+    missed: u32,
+    confirmed: bool,
+    /// Gesture of this frame, `Gesture::None` when the person wasn't seen.
+    pub gesture: Gesture,
+    /// Smoothed world orientation of the head, as of the last time it was
+    /// seen.
+    pub head_orientation: Option<Quat>,
+    /// Where the person is pointing in this frame.
+    pub pointing: Option<Line>,
+    /// Covariance of the last observed position.
+    pub position_covariance: Option<Mat3A>,
+    position_filter: OneEuroFilter,
+    pose_filter: SlerpFilter,
+    /// Seconds since the position and head orientation were last fed to the
+    /// filters.
+    since_position: f32,
+    since_pose: f32,
+}
+
+impl Track {
+    fn new(
+        id: TrackId,
+        observation: Observation,
+        config: &TrackingConfig,
+        smoothing: &SmoothingConfig,
+    ) -> Self {
+        let mut track = Self {
+            id,
+            filter: ConstantVelocity::new(observation.position, config),
+            hits: 0,
+            missed: 0,
+            confirmed: config.confirm_hits <= 1,
+            gesture: Gesture::None,
+            head_orientation: None,
+            pointing: None,
+            position_covariance: None,
+            position_filter: OneEuroFilter::new(smoothing.position),
+            pose_filter: SlerpFilter::new(smoothing.orientation),
+            since_position: 0.0,
+            since_pose: 0.0,
+        };
+        track.record(observation);
+        track
+    }
+
+    pub fn velocity(&self) -> Vec3A {
+        self.filter.vel
+    }
+
+    /// Seen in enough frames to be a real person.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    /// Seen in the latest frame.
+    pub fn is_seen(&self) -> bool {
+        self.missed == 0
+    }
+
+    /// Jitter free position for aiming, lags a little behind [`Self::pos`].
+    pub fn smoothed_pos(&self) -> Vec3A {
+        self.position_filter.value().unwrap_or(self.filter.pos)
+    }
+
+    fn observe(&mut self, observation: Observation, config: &TrackingConfig) {
+        self.filter.update(observation.position, config);
+        self.record(observation);
+    }
+
+    fn record(&mut self, observation: Observation) {
+        self.hits += 1;
+        self.missed = 0;
+        self.gesture = observation.gesture;
+        self.pointing = observation.pointing;
+        self.position_covariance = observation.position_covariance;
+
+        self.position_filter
+            .filter(observation.position, self.since_position);
+        self.since_position = 0.0;
+
+        if let Some(orientation) = observation.head_orientation {
+            self.head_orientation = Some(self.pose_filter.filter(orientation, self.since_pose));
+            self.since_pose = 0.0;
+        }
+    }
+}
+
+impl HasGlamPosition for Track {
+    fn pos(&self) -> &Vec3A {
+        &self.filter.pos
+    }
+}
+
+/// Tracks that appeared or went away in an update.
+#[derive(Debug, Default)]
+pub struct TrackChanges {
+    pub born: Vec<TrackId>,
+    pub died: Vec<TrackId>,
+}
+
+/// Follows the triangulated heads from frame to frame, giving each person an
+/// id that stays the same while they're in view.
+#[derive(Debug)]
+pub struct Tracker {
+    config: TrackingConfig,
+    smoothing: SmoothingConfig,
+    tracks: Vec<Track>,
+    next_id: u32,
+}
+
+impl Tracker {
+    pub fn new(config: TrackingConfig, smoothing: SmoothingConfig) -> Self {
+        Self {
+            config,
+            smoothing,
+            tracks: vec![],
+            next_id: 0,
+        }
+    }
+
+    /// Confirmed tracks, seen or coasting.
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter().filter(|t| t.confirmed)
+    }
+
+    pub fn get(&self, id: TrackId) -> Option<&Track> {
+        self.tracks().find(|t| t.id == id)
+    }
+
+    /// Advances the tracks by `dt` seconds and matches them with the people
+    /// seen in this frame.
+    pub fn update(&mut self, dt: f32, observations: Vec<Observation>) -> TrackChanges {
+        let config = &self.config;
+        let mut changes = TrackChanges::default();
+
+        for track in &mut self.tracks {
+            track.filter.predict(dt, config);
+            track.since_position += dt;
+            track.since_pose += dt;
+        }
+
+        let cost: Vec<Vec<f32>> = self
+            .tracks
+            .iter()
+            .map(|t| {
+                observations
+                    .iter()
+                    .map(|o| t.filter.mahalanobis(o.position, config))
+                    .collect()
+            })
+            .collect();
+        let pairs = assign(&cost, config.gate);
+
+        let mut observations: Vec<Option<Observation>> =
+            observations.into_iter().map(Some).collect();
+
+        for (i, track) in self.tracks.iter_mut().enumerate() {
+            match pairs.iter().find(|(t, _)| *t == i) {
+                Some((_, o)) => {
+                    track.observe(observations[*o].take().unwrap(), config);
+                    if !track.confirmed && track.hits >= config.confirm_hits {
+                        track.confirmed = true;
+                        changes.born.push(track.id);
+                    }
+                }
+                None => {
+                    track.missed += 1;
+                    track.gesture = Gesture::None;
+                    track.pointing = None;
+                }
+            }
+        }
+
+        self.tracks.retain(|t| {
+            // a tentative track that drops out was most likely noise
+            let alive = if t.confirmed {
+                t.missed <= config.max_missed
+            } else {
+                t.missed == 0
+            };
+            if !alive && t.confirmed {
+                changes.died.push(t.id);
+            }
+            alive
+        });
+
+        for observation in observations.into_iter().flatten() {
+            let track = Track::new(TrackId(self.next_id), observation, config, &self.smoothing);
+            self.next_id += 1;
+
+            if track.confirmed {
+                changes.born.push(track.id);
+            }
+
+            self.tracks.push(track);
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GestureKind;
+
+    const DT: f32 = 0.1;
+
+    fn seen(x: f32, y: f32) -> Observation {
+        Observation {
+            position: Vec3A::new(x, y, 1.7),
+            position_covariance: None,
+            gesture: Gesture::None,
+            head_orientation: None,
+            pointing: None,
+        }
+    }
+
+    #[test]
+    fn keeps_ids_of_crossing_people() {
+        let mut tracker = Tracker::new(TrackingConfig::default(), SmoothingConfig::default());
+        let mut born = vec![];
+
+        // two people walking past each other along y = 0 and y = 0.6
+        for frame in 0..30 {
+            let t = frame as f32 * DT;
+            let mut observations = vec![seen(t, 0.0), seen(3.0 - t, 0.6)];
+            if frame % 2 == 1 {
+                observations.reverse();
+            }
+            born.extend(tracker.update(DT, observations).born);
+        }
+
+        assert_eq!(born.len(), 2);
+        let tracks: Vec<_> = tracker.tracks().collect();
+        assert_eq!(tracks.len(), 2);
+
+        let walker = tracks.iter().find(|t| t.id == born[0]).unwrap();
+        assert!(walker.pos().abs_diff_eq(Vec3A::new(2.9, 0.0, 1.7), 0.05));
+        assert!(walker.velocity().abs_diff_eq(Vec3A::X, 0.1));
+    }
+
+    #[test]
+    fn births_and_deaths() {
+        let config = TrackingConfig {
+            confirm_hits: 2,
+            max_missed: 2,
+            ..Default::default()
+        };
+        let mut tracker = Tracker::new(config, SmoothingConfig::default());
+
+        // a one frame blip never becomes a track
+        assert!(tracker.update(DT, vec![seen(5.0, 5.0)]).born.is_empty());
+        assert!(tracker.update(DT, vec![]).born.is_empty());
+        assert_eq!(tracker.tracks().count(), 0);
+
+        tracker.update(DT, vec![seen(1.0, 1.0)]);
+        let born = tracker.update(DT, vec![seen(1.0, 1.0)]).born;
+        assert_eq!(born.len(), 1);
+
+        // coasts through a short gap, keeping its id
+        tracker.update(DT, vec![]);
+        tracker.update(DT, vec![]);
+        assert!(tracker.update(DT, vec![seen(1.0, 1.0)]).born.is_empty());
+        assert!(tracker.get(born[0]).unwrap().is_seen());
+
+        for _ in 0..2 {
+            assert!(tracker.update(DT, vec![]).died.is_empty());
+        }
+        assert_eq!(tracker.update(DT, vec![]).died, born);
+        assert_eq!(tracker.tracks().count(), 0);
+    }
+
+    #[test]
+    fn carries_observations() {
+        let config = TrackingConfig {
+            confirm_hits: 1,
+            ..Default::default()
+        };
+        let mut tracker = Tracker::new(config, SmoothingConfig::default());
+
+        let mut observation = seen(0.0, 0.0);
+        observation.gesture = Gesture::Toggle;
+        observation.head_orientation = Some(Quat::from_rotation_z(0.5));
+        let id = tracker.update(DT, vec![observation]).born[0];
+
+        let track = tracker.get(id).unwrap();
+        assert!(track.gesture.is(&GestureKind::Toggle));
+
+        tracker.update(DT, vec![seen(0.0, 0.0)]);
+        let track = tracker.get(id).unwrap();
+        assert!(track.gesture.is_none());
+        assert_eq!(track.head_orientation, Some(Quat::from_rotation_z(0.5)));
+    }
+}